@@ -0,0 +1,130 @@
+use super::{cycle, preset::Preset};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Display};
+
+/// Timer of a chain: options over an optional preset, like `rimer add`.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Link {
+    pub preset: Option<String>,
+    pub options: Preset,
+}
+
+/// How many times something runs, written as number or `forever`.
+#[derive(Clone, Copy)]
+pub enum Count {
+    Times(u32),
+    Forever,
+}
+
+/// Timers started one after another, each when the previous one finishes.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Chain {
+    pub links: Vec<Link>,
+    /// Current link.
+    pub index: usize,
+    /// Current round, counts from 1.
+    pub round: u32,
+    pub rounds: Count,
+}
+
+impl Chain {
+    /// Chain moved to the next link, back to the first one when another
+    /// round is due, `None` when it is done.
+    pub fn next(&self) -> Option<Chain> {
+        let (index, round) = if self.index + 1 < self.links.len() {
+            (self.index + 1, self.round)
+        } else if self.rounds.allows(self.round + 1) {
+            (0, self.round + 1)
+        } else {
+            return None;
+        };
+
+        Some(Chain {
+            links: self.links.clone(),
+            index,
+            round,
+            rounds: self.rounds,
+        })
+    }
+
+    pub fn link(&self) -> &Link {
+        &self.links[self.index]
+    }
+}
+
+impl Count {
+    /// Whether `n`th run, counting from 1, is allowed.
+    pub fn allows(self, n: u32) -> bool {
+        match self {
+            Self::Times(times) => n <= times,
+            Self::Forever => true,
+        }
+    }
+}
+
+impl Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Times(times) => write!(f, "{}", times),
+            Self::Forever => write!(f, "forever"),
+        }
+    }
+}
+
+impl Serialize for Count {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Times(times) => s.serialize_u32(*times),
+            Self::Forever => s.serialize_str("forever"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Count {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Times(u32),
+            Word(String),
+        }
+
+        match Raw::deserialize(d)? {
+            Raw::Times(times) => parse_count(&times.to_string()),
+            Raw::Word(word) => parse_count(&word),
+        }
+        .map_err(D::Error::custom)
+    }
+}
+
+/// Parses `forever` or positive number.
+pub fn parse_count(s: &str) -> Result<Count, String> {
+    match s {
+        "forever" => Ok(Count::Forever),
+        _ => match s.parse::<u32>() {
+            Ok(0) => Err("Count cannot be zero".into()),
+            Ok(times) => Ok(Count::Times(times)),
+            Err(_) => Err(format!("Count must be a number or forever: {}", s)),
+        },
+    }
+}
+
+/// Parses preset name or explicit timer like `tea=3m`.
+pub fn parse_link(s: &str) -> Result<Link, String> {
+    if !s.contains('=') {
+        return Ok(Link {
+            preset: Some(s.into()),
+            options: Preset::default(),
+        });
+    }
+
+    let phase = cycle::parse_phase(s)?;
+    Ok(Link {
+        preset: None,
+        options: Preset {
+            name: Some(phase.label),
+            duration: Some(phase.duration),
+            ..Preset::default()
+        },
+    })
+}
@@ -1,24 +1,79 @@
-use super::{request::Request, response::Response, socket};
-use std::{io::stdout, net::Shutdown};
+use super::{
+    clock,
+    request::Request,
+    response::{Reply, Response},
+    server,
+    snapshot::Format,
+    socket,
+};
+use std::{io::stdout, net::Shutdown, path::Path, time::SystemTime};
 
-pub fn run(request: Request) -> std::io::Result<()> {
-    let stream = socket::stream()?;
+pub fn run(request: Request, socket: &Path) -> std::io::Result<()> {
+    let stream = match socket::stream(socket) {
+        Err(error) if matches!(request, Request::Status) => {
+            return Err(std::io::Error::new(
+                error.kind(),
+                format!("Server is not running on {}", socket.display()),
+            ));
+        }
+        stream => stream?,
+    };
     serde_json::to_writer(&stream, &request)?;
     stream.shutdown(Shutdown::Write)?;
 
     let response: Response = serde_json::from_reader(&stream)?;
     match response {
-        Ok(Some(snapshots)) => {
-            if let Request::Report { json: true } = request {
+        Ok(Some(Reply::Presets(presets))) => {
+            if let Request::Presets { json: true } = request {
+                serde_json::to_writer(stdout(), &presets)?;
+            } else {
+                for (name, preset) in presets {
+                    println!("{}: {}", name, preset);
+                }
+            }
+        }
+        Ok(Some(Reply::Schedules(schedules))) => {
+            if let Request::Schedules { json: true } = request {
+                serde_json::to_writer(stdout(), &schedules)?;
+            } else {
+                let now = SystemTime::now();
+                for (name, schedule) in schedules {
+                    match schedule.every.next(now) {
+                        Some(next) => {
+                            println!("{}: {}, next {}", name, schedule, clock::format(next))
+                        }
+                        None => println!("{}: {}", name, schedule),
+                    }
+                }
+            }
+        }
+        Ok(Some(Reply::Report(snapshots))) => match request {
+            Request::Report { json: true, .. } => {
                 let mut lock = stdout();
                 serde_json::to_writer(&mut lock, &snapshots)?;
-            } else {
+            }
+            Request::Report { human, millis, .. } => {
+                let format = match (human, millis) {
+                    (true, _) => Format::Human,
+                    (false, true) => Format::Millis,
+                    (false, false) => Format::Seconds,
+                };
                 for snapshot in snapshots {
-                    println!("{}", snapshot);
+                    println!("{}", snapshot.display(format));
+                }
+            }
+            _ => unreachable!(),
+        },
+        Ok(None) => {
+            if let Request::Status = request {
+                match server::read_pid(socket) {
+                    Some(pid) => {
+                        println!("Server is running on {} (pid {})", socket.display(), pid)
+                    }
+                    None => println!("Server is running on {}", socket.display()),
                 }
             }
         }
-        Ok(None) => (),
         Err(error) => eprintln!("{}", error),
     }
     Ok(())
@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How running timers count time.  Both are monotonic, so setting system
+/// clock does not move timers, they differ in suspend.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Clock {
+    /// Time spent in suspend counts, like on a wall clock.
+    #[default]
+    Wall,
+    /// Only time system is awake counts.
+    Active,
+}
+
+impl Clock {
+    /// Reading of the clock, since some unspecified moment.
+    pub fn now(self) -> Duration {
+        let id = match self {
+            Self::Wall => BOOTTIME,
+            Self::Active => libc::CLOCK_MONOTONIC,
+        };
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe { libc::clock_gettime(id, &mut ts) };
+        Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const BOOTTIME: libc::clockid_t = libc::CLOCK_BOOTTIME;
+/// Other systems have no clock that is both monotonic and counts suspend.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const BOOTTIME: libc::clockid_t = libc::CLOCK_MONOTONIC;
+
+impl Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Wall => write!(f, "wall"),
+            Self::Active => write!(f, "active"),
+        }
+    }
+}
+
+impl FromStr for Clock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wall" => Ok(Self::Wall),
+            "active" => Ok(Self::Active),
+            _ => Err(format!("Clock must be wall or active: {}", s)),
+        }
+    }
+}
+
+/// Local time of day like `09:00` or `09:00:30`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Time {
+    pub hour: i32,
+    pub min: i32,
+    pub sec: i32,
+}
+
+impl Time {
+    pub fn parse(s: &str) -> Result<Time, String> {
+        let invalid = || format!("Invalid time, expected HH:MM or HH:MM:SS: {}", s);
+        let (hour, min, sec) = match numbers(s, ':').ok_or_else(invalid)?[..] {
+            [hour, min] => (hour, min, 0),
+            [hour, min, sec] => (hour, min, sec),
+            _ => return Err(invalid()),
+        };
+        if hour > 23 || min > 59 || sec > 59 {
+            return Err(invalid());
+        }
+        Ok(Time { hour, min, sec })
+    }
+
+    fn set(self, tm: &mut libc::tm) {
+        tm.tm_hour = self.hour;
+        tm.tm_min = self.min;
+        tm.tm_sec = self.sec;
+        // Let mktime figure out whether daylight saving time is in effect.
+        tm.tm_isdst = -1;
+    }
+}
+
+impl Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.min)?;
+        if self.sec != 0 {
+            write!(f, ":{:02}", self.sec)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses local wall-clock time like `14:30`, `14:30:15` or
+/// `2026-10-18T17:00`.  Time without date is the next such time, either today
+/// or tomorrow.
+pub fn parse(s: &str) -> Result<SystemTime, String> {
+    let invalid = || format!("Invalid time, expected HH:MM or YYYY-MM-DDTHH:MM: {}", s);
+
+    let (date, time) = match s.find(['T', 't', ' ']) {
+        Some(i) => (Some(&s[..i]), &s[i + 1..]),
+        None => (None, s),
+    };
+    let time = Time::parse(time).map_err(|_| invalid())?;
+
+    let now = SystemTime::now();
+    let mut tm = localtime(now);
+    if let Some(date) = date {
+        let (year, month, day) = match numbers(date, '-').as_deref() {
+            Some(&[year, month, day]) if (1..=12).contains(&month) && (1..=31).contains(&day) => {
+                (year, month, day)
+            }
+            _ => return Err(invalid()),
+        };
+        tm.tm_year = year - 1900;
+        tm.tm_mon = month - 1;
+        tm.tm_mday = day;
+    }
+
+    let day = (tm.tm_year, tm.tm_mon, tm.tm_mday);
+    time.set(&mut tm);
+    let mut target = mktime(&mut tm).ok_or_else(invalid)?;
+    // mktime rolls days like February 31 over into the next month.
+    if (tm.tm_year, tm.tm_mon, tm.tm_mday) != day {
+        return Err(invalid());
+    }
+
+    if date.is_none() && target <= now {
+        tm.tm_mday += 1;
+        time.set(&mut tm);
+        target = mktime(&mut tm).ok_or_else(invalid)?;
+    }
+    Ok(target)
+}
+
+/// First wall-clock `time` later than `after` on a day for which `on_day`
+/// holds, days are numbered from Sunday as 0.  `None` if there is none within
+/// a week.
+pub fn next(time: Time, on_day: impl Fn(i32) -> bool, after: SystemTime) -> Option<SystemTime> {
+    let today = localtime(after);
+    (0..8).find_map(|offset| {
+        let mut tm = today;
+        tm.tm_mday += offset;
+        time.set(&mut tm);
+        // mktime normalizes the date and fills in its day of week.
+        let target = mktime(&mut tm)?;
+        (target > after && on_day(tm.tm_wday)).then_some(target)
+    })
+}
+
+/// Formats `time` as local `YYYY-MM-DD HH:MM`.
+pub fn format(time: SystemTime) -> String {
+    let tm = localtime(time);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min
+    )
+}
+
+fn numbers(s: &str, separator: char) -> Option<Vec<i32>> {
+    s.split(separator)
+        .map(|part| {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                None
+            } else {
+                part.parse().ok()
+            }
+        })
+        .collect()
+}
+
+fn localtime(time: SystemTime) -> libc::tm {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&secs, &mut tm) };
+    tm
+}
+
+fn mktime(tm: &mut libc::tm) -> Option<SystemTime> {
+    match unsafe { libc::mktime(tm) } {
+        -1 => None,
+        secs => Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> SystemTime {
+        parse(s).unwrap()
+    }
+
+    fn time(s: &str) -> Time {
+        Time::parse(s).unwrap()
+    }
+
+    #[test]
+    fn time_parse_display_round_trips() {
+        assert_eq!(time("9:05").to_string(), "09:05");
+        assert_eq!(time("09:00:30").to_string(), "09:00:30");
+        assert_eq!(time("23:59:59").to_string(), "23:59:59");
+        assert_eq!(time("00:00:00").to_string(), "00:00");
+
+        for s in &[
+            "", "12", "24:00", "12:60", "12:00:60", "1:2:3:4", "ab:cd", "-1:00", "12:",
+        ] {
+            assert!(Time::parse(s).is_err(), "{} was accepted", s);
+        }
+    }
+
+    #[test]
+    fn dated_time() {
+        assert_eq!(format(at("2026-10-18T17:00")), "2026-10-18 17:00");
+        assert_eq!(format(at("2026-10-18 17:00:30")), "2026-10-18 17:00");
+        assert_eq!(format(at("2027-01-01t00:05")), "2027-01-01 00:05");
+        assert_eq!(format(at("2028-02-29T10:00")), "2028-02-29 10:00");
+
+        for s in &[
+            "2026-13-01T10:00",
+            "2026-10-32T10:00",
+            "2027-02-31T10:00",
+            "2027-02-29T10:00",
+            "2026-04-31T10:00",
+            "2026-10T10:00",
+            "17",
+            "tomorrow",
+        ] {
+            assert!(parse(s).is_err(), "{} was accepted", s);
+        }
+    }
+
+    #[test]
+    fn undated_time_is_within_a_day() {
+        let now = SystemTime::now();
+        let target = at("12:00");
+        assert!(target > now);
+        assert!(target <= now + Duration::from_secs(24 * 60 * 60));
+    }
+
+    #[test]
+    fn next_time_on_given_days() {
+        // Wednesday.
+        let after = at("2026-10-14T12:00");
+        let next = |t: &str, on_day: fn(i32) -> bool| next(time(t), on_day, after).map(format);
+
+        assert_eq!(next("13:00", |_| true).unwrap(), "2026-10-14 13:00");
+        assert_eq!(next("12:00", |_| true).unwrap(), "2026-10-15 12:00");
+        assert_eq!(next("09:00", |_| true).unwrap(), "2026-10-15 09:00");
+        assert_eq!(next("09:00", |day| day == 5).unwrap(), "2026-10-16 09:00");
+        assert_eq!(next("13:00", |day| day == 3).unwrap(), "2026-10-14 13:00");
+        assert_eq!(next("09:00", |day| day == 3).unwrap(), "2026-10-21 09:00");
+        assert_eq!(next("12:00", |day| day == 3).unwrap(), "2026-10-21 12:00");
+        assert_eq!(next("09:00", |_| false), None);
+    }
+
+    #[test]
+    fn next_time_across_month_and_year() {
+        let next = |t: &str, after: &str| next(time(t), |_| true, at(after)).map(format);
+
+        assert_eq!(
+            next("01:00", "2026-10-31T23:00").unwrap(),
+            "2026-11-01 01:00"
+        );
+        assert_eq!(
+            next("00:15", "2026-12-31T23:30").unwrap(),
+            "2027-01-01 00:15"
+        );
+        assert_eq!(
+            next("08:00", "2028-02-28T09:00").unwrap(),
+            "2028-02-29 08:00"
+        );
+    }
+}
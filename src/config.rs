@@ -1,15 +1,97 @@
-use super::request::Request;
-use clap::{App, Arg};
-use std::{env, path::Path, time::Duration};
+pub mod file;
+
+use super::{
+    chain::{self, Count},
+    clock::{self, Clock},
+    cycle::{self, Phase},
+    duration,
+    event::{Event, Hooks},
+    preset::Preset,
+    request::Request,
+    schedule::{Every, Schedule},
+    server, socket,
+};
+use clap::{App, Arg, ArgGroup, ArgMatches};
+use std::{
+    env,
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Hook event, argument name, long option and help.
+const HOOKS: [(Event, &str, &str, &str); 9] = [
+    (
+        Event::Start,
+        "ON_START",
+        "on-start",
+        "Called when timer starts",
+    ),
+    (
+        Event::Step,
+        "ON_STEP",
+        "on-step",
+        "Called on every timer step",
+    ),
+    (
+        Event::Pause,
+        "ON_PAUSE",
+        "on-pause",
+        "Called when timer is paused",
+    ),
+    (
+        Event::Resume,
+        "ON_RESUME",
+        "on-resume",
+        "Called when timer is resumed",
+    ),
+    (
+        Event::Finish,
+        "ON_FINISH",
+        "on-finish",
+        "Called when timer runs out",
+    ),
+    (
+        Event::Halt,
+        "ON_HALT",
+        "on-halt",
+        "Called when timer is halted",
+    ),
+    (
+        Event::Change,
+        "ON_CHANGE",
+        "on-change",
+        "Called when timer is extended, shortened, renamed or set",
+    ),
+    (
+        Event::Missed,
+        "ON_MISSED",
+        "on-missed",
+        "Called along with on-finish when timer finishes late, like after suspend",
+    ),
+    (
+        Event::Jump,
+        "ON_JUMP",
+        "on-jump",
+        "Called when system clock is set or system wakes up",
+    ),
+];
 
 pub enum Config {
-    Server { callback: String },
-    Client { request: Request },
+    Server {
+        options: server::Options,
+    },
+    Client {
+        request: Request,
+        socket: PathBuf,
+    },
+    /// `rimer config check`
+    Check,
 }
 
 impl Config {
-    pub fn new() -> Self {
-        let matches = App::new("rimer")
+    pub fn new() -> io::Result<Self> {
+        let app = App::new("rimer")
             .max_term_width(80)
             .version("0.1.1")
             .author("Valeriy Litkovskyy <vlr.ltkvsk@protonmail.com>")
@@ -18,15 +100,33 @@ impl Config {
                 Arg::with_name("COMMAND")
                     .empty_values(false)
                     .help("Command")
-                    .possible_values(&["start", "add", "pause", "resume", "halt", "report", "quit"])
+                    .possible_values(&[
+                        "start", "add", "pause", "resume", "halt", "report", "status", "reload",
+                        "quit", "config", "preset", "presets", "cycle", "skip", "restart",
+                        "schedule", "unschedule", "schedules", "extend", "shorten", "set",
+                        "rename",
+                    ])
                     .required(true)
                     .requires_ifs(&[
-                        ("start", "CALLBACK"),
-                        ("add", "NAME"),
-                        ("add", "DURATION"),
+                        ("config", "CALLBACK"),
+                        ("preset", "PRESET"),
+                        ("cycle", "NAME"),
+                        ("cycle", "PHASES"),
+                        ("skip", "NAME"),
+                        ("restart", "NAME"),
                         ("pause", "NAME"),
                         ("halt", "NAME"),
                         ("resume", "NAME"),
+                        ("schedule", "NAME"),
+                        ("schedule", "EVERY"),
+                        ("unschedule", "NAME"),
+                        ("extend", "NAME"),
+                        ("extend", "BY"),
+                        ("shorten", "NAME"),
+                        ("shorten", "BY"),
+                        ("set", "NAME"),
+                        ("rename", "FROM"),
+                        ("rename", "TO"),
                     ])
                     .value_name("COMMAND"),
             )
@@ -35,9 +135,71 @@ impl Config {
                     .empty_values(false)
                     .help(include_str!("../help/rimer_callback.txt"))
                     .next_line_help(true)
-                    .validator(validate_program)
                     .value_name("CALLBACK"),
             )
+            .arg(
+                Arg::with_name("TIMEOUT")
+                    .default_value("1m")
+                    .empty_values(false)
+                    .help("Callback is killed if it runs longer than <TIMEOUT>")
+                    .long("timeout")
+                    .takes_value(true)
+                    .validator(validate_duration)
+                    .value_name("TIMEOUT"),
+            )
+            .arg(
+                Arg::with_name("JOBS")
+                    .default_value("4")
+                    .empty_values(false)
+                    .help("Maximum number of callbacks running at the same time")
+                    .long("jobs")
+                    .takes_value(true)
+                    .validator(validate_jobs)
+                    .value_name("JOBS"),
+            )
+            .arg(
+                Arg::with_name("SOCKET")
+                    .empty_values(false)
+                    .help("Server socket path (defaults to RIMER_SOCKET if set)")
+                    .long("socket")
+                    .takes_value(true)
+                    .conflicts_with("INSTANCE")
+                    .value_name("SOCKET"),
+            )
+            .arg(
+                Arg::with_name("INSTANCE")
+                    .empty_values(false)
+                    .help("Name of independent server instance")
+                    .long("instance")
+                    .short("i")
+                    .takes_value(true)
+                    .validator(validate_instance)
+                    .value_name("INSTANCE"),
+            )
+            .arg(
+                Arg::with_name("REPLACE")
+                    .help("Replace already running server on start")
+                    .long("replace"),
+            )
+            .arg(
+                Arg::with_name("DAEMON")
+                    .help("Run server in background")
+                    .long("daemon")
+                    .short("D"),
+            )
+            .arg(
+                Arg::with_name("LOG")
+                    .empty_values(false)
+                    .help("Daemon log file (defaults to state directory)")
+                    .long("log")
+                    .takes_value(true)
+                    .value_name("LOG"),
+            )
+            .arg(
+                Arg::with_name("HALT_ON_EXIT")
+                    .help("Halt timers on quit instead of restoring them on next start")
+                    .long("halt-on-exit"),
+            )
             .arg(
                 Arg::with_name("NAME")
                     .empty_values(false)
@@ -47,30 +209,84 @@ impl Config {
                     .takes_value(true)
                     .value_name("NAME"),
             )
+            .arg(
+                Arg::with_name("FROM")
+                    .empty_values(false)
+                    .help("Timer to rename")
+                    .long("from")
+                    .takes_value(true)
+                    .value_name("NAME"),
+            )
+            .arg(
+                Arg::with_name("TO")
+                    .empty_values(false)
+                    .help("New timer name")
+                    .long("to")
+                    .takes_value(true)
+                    .value_name("NAME"),
+            )
             .arg(
                 Arg::with_name("DURATION")
                     .empty_values(false)
-                    .help("Seconds to run (max value unsigned 64bit integer)")
+                    .help("Time to run: seconds, 25m, 1h30m, 1:30:00 or PT25M")
                     .long("duration")
                     .short("d")
                     .takes_value(true)
                     .validator(validate_duration)
                     .value_name("DURATION"),
             )
+            .arg(
+                Arg::with_name("STOPWATCH")
+                    .help("Count up without end instead of running for <DURATION>")
+                    .long("stopwatch")
+                    .short("w"),
+            )
+            .arg(
+                Arg::with_name("BY")
+                    .empty_values(false)
+                    .help("How much to extend or shorten timer by")
+                    .long("by")
+                    .takes_value(true)
+                    .validator(validate_duration)
+                    .value_name("DURATION"),
+            )
+            .arg(
+                Arg::with_name("OVERTIME")
+                    .help("Keep counting after <DURATION> until halted")
+                    .long("overtime")
+                    .conflicts_with_all(&["STOPWATCH", "REPEAT", "THEN"]),
+            )
+            .arg(
+                Arg::with_name("AT")
+                    .empty_values(false)
+                    .help("Run until wall-clock time like 14:30 or 2026-10-18T17:00")
+                    .long("at")
+                    .takes_value(true)
+                    .validator(validate_at)
+                    .value_name("TIME"),
+            )
+            .group(ArgGroup::with_name("LENGTH").args(&["DURATION", "STOPWATCH", "AT"]))
             .arg(
                 Arg::with_name("STEP")
-                    .default_value("10")
                     .empty_values(false)
-                    .help("Callback is called every <STEP> seconds")
+                    .help("Callback is called every <STEP> (same format as <DURATION>, 10s by default)")
                     .long("step")
                     .short("s")
                     .takes_value(true)
                     .validator(validate_duration)
                     .value_name("STEP"),
             )
+            .arg(
+                Arg::with_name("CLOCK")
+                    .empty_values(false)
+                    .help("Whether time in suspend counts (wall, by default) or not (active)")
+                    .long("clock")
+                    .possible_values(&["wall", "active"])
+                    .takes_value(true)
+                    .value_name("CLOCK"),
+            )
             .arg(
                 Arg::with_name("CALLBACK_ARG")
-                    .default_value("")
                     .empty_values(true)
                     .help("Callback arg that will be a fifth argument to callback")
                     .long("arg")
@@ -78,72 +294,539 @@ impl Config {
                     .takes_value(true)
                     .value_name("CALLBACK_ARG"),
             )
+            .arg(
+                Arg::with_name("TIMER_CALLBACK")
+                    .empty_values(false)
+                    .help("Timer specific callback used instead of server <CALLBACK>")
+                    .long("callback")
+                    .short("c")
+                    .takes_value(true)
+                    .validator(validate_program)
+                    .value_name("TIMER_CALLBACK"),
+            )
+            .arg(
+                Arg::with_name("PHASES")
+                    .empty_values(false)
+                    .help("Cycle phases like work=25m,break=5m")
+                    .long("phases")
+                    .takes_value(true)
+                    .validator(validate_phases)
+                    .value_name("PHASES"),
+            )
+            .arg(
+                Arg::with_name("ROUNDS")
+                    .default_value("1")
+                    .empty_values(false)
+                    .help("How many times cycle runs its <PHASES>")
+                    .long("rounds")
+                    .takes_value(true)
+                    .validator(validate_rounds)
+                    .value_name("ROUNDS"),
+            )
+            .arg(
+                Arg::with_name("LONG_BREAK")
+                    .empty_values(false)
+                    .help("Phase called long-break that ends cycle after all rounds")
+                    .long("long-break")
+                    .takes_value(true)
+                    .validator(validate_duration)
+                    .value_name("DURATION"),
+            )
+            .arg(
+                Arg::with_name("THEN")
+                    .empty_values(false)
+                    .help("Timer to start when the previous one finishes: preset or name=duration")
+                    .long("then")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .takes_value(true)
+                    .validator(validate_link)
+                    .value_name("NEXT"),
+            )
+            .arg(
+                Arg::with_name("REPEAT")
+                    .empty_values(false)
+                    .help("How many times timer runs, number or forever")
+                    .long("repeat")
+                    .takes_value(true)
+                    .validator(validate_count)
+                    .value_name("COUNT"),
+            )
+            .arg(
+                Arg::with_name("GAP")
+                    .empty_values(false)
+                    .help("Pause between runs of repeating timer")
+                    .long("gap")
+                    .takes_value(true)
+                    .validator(validate_duration)
+                    .value_name("GAP"),
+            )
+            .arg(
+                Arg::with_name("LOOP")
+                    .empty_values(false)
+                    .help("How many times timer and its --then timers run, number or forever (1 by default)")
+                    .long("loop")
+                    .requires("THEN")
+                    .takes_value(true)
+                    .validator(validate_count)
+                    .value_name("COUNT"),
+            )
+            .arg(
+                Arg::with_name("EVERY")
+                    .empty_values(false)
+                    .help("When schedule starts its timer: 09:00, weekdays 09:00 or mon,wed-fri 18:30")
+                    .long("every")
+                    .takes_value(true)
+                    .validator(validate_every)
+                    .value_name("WHEN"),
+            )
+            .arg(
+                Arg::with_name("PRESET")
+                    .empty_values(false)
+                    .help("Preset to fill timer options on add, or to register on preset")
+                    .long("preset")
+                    .short("p")
+                    .takes_value(true)
+                    .value_name("PRESET"),
+            )
             .arg(
                 Arg::with_name("JSON")
-                    .help("Report json")
+                    .help("Report json or, on start, pass snapshot json to callback stdin")
                     .short("j")
                     .long("json"),
             )
+            .arg(
+                Arg::with_name("MILLIS")
+                    .help("Durations in milliseconds: on start for callbacks, on report for output")
+                    .short("M")
+                    .long("millis"),
+            )
+            .arg(
+                Arg::with_name("HUMAN")
+                    .help("Report durations like 1h30m instead of seconds")
+                    .short("H")
+                    .long("human"),
+            );
+
+        let matches = HOOKS
+            .iter()
+            .fold(app, |app, &(_, name, long, help)| {
+                app.arg(
+                    Arg::with_name(name)
+                        .empty_values(false)
+                        .help(help)
+                        .long(long)
+                        .takes_value(true)
+                        .validator(validate_program)
+                        .value_name("PROGRAM"),
+                )
+            })
             .get_matches();
 
         let value_of = |s| matches.value_of(s).unwrap();
-        let num_value_of = |s| value_of(s).parse::<u64>().unwrap();
+        let duration_of = |s| duration::parse(value_of(s)).unwrap();
 
-        match value_of("COMMAND") {
+        if value_of("COMMAND") == "config" {
+            if value_of("CALLBACK") != "check" {
+                clap::Error::value_validation_auto("Config command must be 'check'".into()).exit();
+            }
+            return Ok(Self::Check);
+        }
+        let callback = matches.value_of("CALLBACK").map(|callback| {
+            if let Err(error) = validate_program(callback.into()) {
+                clap::Error::value_validation_auto(error).exit();
+            }
+            program(callback)
+        });
+
+        let file = file::load()?;
+
+        let socket = match (matches.value_of("SOCKET"), matches.value_of("INSTANCE")) {
+            (Some(path), _) => absolute(path)?,
+            (None, Some(instance)) => socket::path(Some(instance))?,
+            (None, None) => match (env::var_os("RIMER_SOCKET"), file.socket) {
+                (Some(path), _) if !path.is_empty() => PathBuf::from(path),
+                (_, Some(path)) => path,
+                _ => socket::path(None)?,
+            },
+        };
+
+        let config = match value_of("COMMAND") {
             "start" => Self::Server {
-                callback: value_of("CALLBACK").into(),
+                options: server::Options {
+                    callback,
+                    hooks: hooks(&matches),
+                    clock: clock(&matches),
+                    timeout: duration_of("TIMEOUT"),
+                    jobs: value_of("JOBS").parse().unwrap(),
+                    json: matches.is_present("JSON"),
+                    millis: matches.is_present("MILLIS"),
+                    socket,
+                    replace: matches.is_present("REPLACE"),
+                    daemon: matches.is_present("DAEMON"),
+                    halt_on_exit: matches.is_present("HALT_ON_EXIT"),
+                    log: matches.value_of("LOG").map(absolute).transpose()?,
+                },
             },
-            "add" => Self::Client {
-                request: Request::Add {
+            "add" => {
+                let complete = matches.is_present("NAME") && matches.is_present("LENGTH");
+                if !complete && !matches.is_present("PRESET") {
+                    clap::Error::with_description(
+                        "add requires --name and --duration or --stopwatch unless --preset is given",
+                        clap::ErrorKind::MissingRequiredArgument,
+                    )
+                    .exit();
+                }
+                // Checked by hand, clap would spread the conflict to all of <LENGTH>.
+                for &other in &["REPEAT", "LOOP"] {
+                    if matches.is_present("AT") && matches.is_present(other) {
+                        clap::Error::with_description(
+                            &format!("--at cannot be used with --{}", other.to_lowercase()),
+                            clap::ErrorKind::ArgumentConflict,
+                        )
+                        .exit();
+                    }
+                }
+                Self::Client {
+                    request: Request::Add {
+                        preset: matches.value_of("PRESET").map(Into::into),
+                        options: preset(&matches),
+                        at: matches.value_of("AT").map(|at| clock::parse(at).unwrap()),
+                        then: matches
+                            .values_of("THEN")
+                            .into_iter()
+                            .flatten()
+                            .map(|link| chain::parse_link(link).unwrap())
+                            .collect(),
+                        rounds: matches
+                            .value_of("LOOP")
+                            .map_or(Count::Times(1), |count| chain::parse_count(count).unwrap()),
+                    },
+                    socket,
+                }
+            }
+            "preset" => Self::Client {
+                request: Request::Preset {
+                    name: value_of("PRESET").into(),
+                    preset: preset(&matches),
+                },
+                socket,
+            },
+            "schedule" => {
+                let length = matches.is_present("DURATION") || matches.is_present("STOPWATCH");
+                if !length && !matches.is_present("PRESET") {
+                    clap::Error::with_description(
+                        "schedule requires --duration or --stopwatch unless --preset is given",
+                        clap::ErrorKind::MissingRequiredArgument,
+                    )
+                    .exit();
+                }
+                Self::Client {
+                    request: Request::Schedule {
+                        name: value_of("NAME").into(),
+                        schedule: Schedule {
+                            every: Every::parse(value_of("EVERY")).unwrap(),
+                            preset: matches.value_of("PRESET").map(Into::into),
+                            // Timer is named after schedule.
+                            options: Preset {
+                                name: None,
+                                ..preset(&matches)
+                            },
+                        },
+                    },
+                    socket,
+                }
+            }
+            "unschedule" => Self::Client {
+                request: Request::Unschedule {
                     name: value_of("NAME").into(),
-                    duration: Duration::from_secs(num_value_of("DURATION")),
-                    step: Duration::from_secs(num_value_of("STEP")),
-                    arg: value_of("CALLBACK_ARG").into(),
                 },
+                socket,
+            },
+            "schedules" => Self::Client {
+                request: Request::Schedules {
+                    json: matches.is_present("JSON"),
+                },
+                socket,
+            },
+            "presets" => Self::Client {
+                request: Request::Presets {
+                    json: matches.is_present("JSON"),
+                },
+                socket,
+            },
+            "cycle" => Self::Client {
+                request: Request::Cycle {
+                    name: value_of("NAME").into(),
+                    phases: phases(&matches),
+                    step: matches.value_of("STEP").map(|_| duration_of("STEP")),
+                    clock: clock(&matches),
+                    arg: matches.value_of("CALLBACK_ARG").map(Into::into),
+                    callback: matches.value_of("TIMER_CALLBACK").map(program),
+                    hooks: hooks(&matches),
+                },
+                socket,
+            },
+            "extend" => Self::Client {
+                request: Request::Extend {
+                    name: value_of("NAME").into(),
+                    by: duration_of("BY"),
+                },
+                socket,
+            },
+            "shorten" => Self::Client {
+                request: Request::Shorten {
+                    name: value_of("NAME").into(),
+                    by: duration_of("BY"),
+                },
+                socket,
+            },
+            "set" => {
+                let options = ["DURATION", "STEP", "CALLBACK_ARG"];
+                if !options.iter().any(|option| matches.is_present(option)) {
+                    clap::Error::with_description(
+                        "set requires --duration, --step or --arg",
+                        clap::ErrorKind::MissingRequiredArgument,
+                    )
+                    .exit();
+                }
+                let options = preset(&matches);
+                Self::Client {
+                    request: Request::Set {
+                        name: value_of("NAME").into(),
+                        duration: options.duration,
+                        step: options.step,
+                        arg: options.arg,
+                    },
+                    socket,
+                }
+            }
+            "rename" => Self::Client {
+                request: Request::Rename {
+                    from: value_of("FROM").into(),
+                    to: value_of("TO").into(),
+                },
+                socket,
+            },
+            "skip" => Self::Client {
+                request: Request::Skip {
+                    name: value_of("NAME").into(),
+                },
+                socket,
+            },
+            "restart" => Self::Client {
+                request: Request::Restart {
+                    name: value_of("NAME").into(),
+                },
+                socket,
             },
             "pause" => Self::Client {
                 request: Request::Pause {
                     name: value_of("NAME").into(),
                 },
+                socket,
             },
             "halt" => Self::Client {
                 request: Request::Halt {
                     name: value_of("NAME").into(),
                 },
+                socket,
             },
             "resume" => Self::Client {
                 request: Request::Resume {
                     name: value_of("NAME").into(),
                 },
+                socket,
             },
             "report" => Self::Client {
                 request: Request::Report {
                     json: matches.is_present("JSON"),
+                    human: matches.is_present("HUMAN"),
+                    millis: matches.is_present("MILLIS"),
                 },
+                socket,
+            },
+            "reload" => Self::Client {
+                request: Request::Reload {
+                    callback,
+                    hooks: hooks(&matches),
+                },
+                socket,
+            },
+            "status" => Self::Client {
+                request: Request::Status,
+                socket,
             },
             "quit" => Self::Client {
                 request: Request::Quit,
+                socket,
             },
             _ => unreachable!(),
+        };
+        Ok(config)
+    }
+}
+
+/// Validates config file and prints what it sets.
+pub fn check() -> Result<(), Box<dyn Error>> {
+    let path = file::path().ok_or("Cannot find config directory")?;
+    let file = file::read(&path)?
+        .ok_or_else(|| format!("Config file {} does not exist", path.display()))?;
+
+    let options = file
+        .presets
+        .values()
+        .chain(file.schedules.values().map(|schedule| &schedule.options));
+    let programs =
+        file.callback.iter().chain(file.hooks.values()).chain(
+            options.flat_map(|options| options.callback.iter().chain(options.hooks.values())),
+        );
+    for program in programs {
+        validate_program(program.clone()).map_err(|error| format!("{}: {}", program, error))?;
+    }
+
+    println!("Config file {} is valid", path.display());
+    if let Some(callback) = &file.callback {
+        println!("callback: {}", callback);
+    }
+    if let Some(step) = file.step {
+        println!("step: {}", duration::format(step));
+    }
+    if let Some(clock) = file.clock {
+        println!("clock: {}", clock);
+    }
+    if let Some(socket) = &file.socket {
+        println!("socket: {}", socket.display());
+    }
+    print_hooks("", &file.hooks);
+    for (name, preset) in &file.presets {
+        println!("preset {}: {}", name, preset);
+    }
+    for (name, schedule) in &file.schedules {
+        println!("schedule {}: {}", name, schedule);
+    }
+    Ok(())
+}
+
+fn print_hooks(indent: &str, hooks: &Hooks) {
+    for (event, _, long, _) in &HOOKS {
+        if let Some(hook) = hooks.get(event) {
+            println!("{}{}: {}", indent, long, hook);
         }
     }
 }
 
+/// Timer options given on command line.
+fn preset(matches: &ArgMatches) -> Preset {
+    let duration_of = |s| matches.value_of(s).map(|d| duration::parse(d).unwrap());
+    Preset {
+        name: matches.value_of("NAME").map(Into::into),
+        duration: duration_of("DURATION"),
+        stopwatch: matches.is_present("STOPWATCH"),
+        overtime: matches.is_present("OVERTIME"),
+        step: duration_of("STEP"),
+        clock: clock(matches),
+        repeat: matches
+            .value_of("REPEAT")
+            .map(|count| chain::parse_count(count).unwrap()),
+        gap: duration_of("GAP"),
+        arg: matches.value_of("CALLBACK_ARG").map(Into::into),
+        callback: matches.value_of("TIMER_CALLBACK").map(program),
+        hooks: hooks(matches),
+    }
+}
+
+/// Cycle phases: `--phases` repeated `--rounds` times, then `--long-break`.
+fn phases(matches: &ArgMatches) -> Vec<Phase> {
+    let round = cycle::parse(matches.value_of("PHASES").unwrap()).unwrap();
+    let rounds: usize = matches.value_of("ROUNDS").unwrap().parse().unwrap();
+    let mut phases: Vec<Phase> = (0..rounds).flat_map(|_| round.clone()).collect();
+    if let Some(length) = matches.value_of("LONG_BREAK") {
+        phases.push(Phase {
+            label: "long-break".into(),
+            duration: duration::parse(length).unwrap(),
+        });
+    }
+    phases
+}
+
+fn clock(matches: &ArgMatches) -> Option<Clock> {
+    matches
+        .value_of("CLOCK")
+        .map(|clock| clock.parse().unwrap())
+}
+
+fn hooks(matches: &ArgMatches) -> Hooks {
+    HOOKS
+        .iter()
+        .filter_map(|&(event, name, _, _)| Some((event, program(matches.value_of(name)?))))
+        .collect()
+}
+
+fn absolute(p: &str) -> io::Result<PathBuf> {
+    Ok(env::current_dir()?.join(p))
+}
+
+/// Makes relative program paths usable from the server working directory.
+fn program(p: &str) -> String {
+    if p.contains('/') {
+        if let Ok(path) = fs::canonicalize(p) {
+            return path.to_string_lossy().into();
+        }
+    }
+    p.into()
+}
+
 type ClapResult = Result<(), String>;
 
 fn validate_duration(s: String) -> ClapResult {
-    match s.parse::<u64>() {
-        Ok(num) => {
-            if num == 0 {
-                Err("Duration cannot be zero".into())
-            } else {
-                Ok(())
-            }
-        }
+    duration::parse(&s).map(|_| ())
+}
+
+fn validate_jobs(s: String) -> ClapResult {
+    match s.parse::<usize>() {
+        Ok(0) => Err("Jobs cannot be zero".into()),
+        Ok(_) => Ok(()),
         Err(e) => Err(e.to_string()),
     }
 }
 
+fn validate_phases(s: String) -> ClapResult {
+    cycle::parse(&s).map(|_| ())
+}
+
+fn validate_rounds(s: String) -> ClapResult {
+    match s.parse::<usize>() {
+        Ok(0) => Err("Rounds cannot be zero".into()),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn validate_at(s: String) -> ClapResult {
+    clock::parse(&s).map(|_| ())
+}
+
+fn validate_every(s: String) -> ClapResult {
+    Every::parse(&s).map(|_| ())
+}
+
+fn validate_link(s: String) -> ClapResult {
+    chain::parse_link(&s).map(|_| ())
+}
+
+fn validate_count(s: String) -> ClapResult {
+    chain::parse_count(&s).map(|_| ())
+}
+
+fn validate_instance(s: String) -> ClapResult {
+    if s.contains('/') {
+        Err("Instance name cannot contain '/'".into())
+    } else {
+        Ok(())
+    }
+}
+
 fn validate_program(p: String) -> ClapResult {
     if Path::new(&p).exists() {
         return Ok(());
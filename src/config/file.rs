@@ -0,0 +1,98 @@
+use crate::{
+    clock::Clock,
+    duration,
+    event::{self, Hooks},
+    preset::Presets,
+    schedule::Schedules,
+};
+use serde::Deserialize;
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Defaults read from `$XDG_CONFIG_HOME/rimer/config`, a json object like
+///
+/// ```json
+/// {
+///     "callback": "notify-timer",
+///     "step": "5s",
+///     "clock": "active",
+///     "socket": "/run/user/1000/rimer.socket",
+///     "hooks": { "finish": "play-sound" },
+///     "presets": { "tea": { "duration": "3m", "arg": "green" } },
+///     "schedules": { "standup": { "every": "weekdays 09:00", "preset": "tea" } }
+/// }
+/// ```
+///
+/// Every key is optional, command line options take precedence.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct File {
+    pub callback: Option<String>,
+    #[serde(default, with = "duration::optional")]
+    pub step: Option<Duration>,
+    pub clock: Option<Clock>,
+    pub socket: Option<PathBuf>,
+    #[serde(default, with = "event::by_name")]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub presets: Presets,
+    #[serde(default)]
+    pub schedules: Schedules,
+}
+
+pub fn path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("rimer").join("config"))
+}
+
+/// Reads config file, missing file gives empty config.
+pub fn load() -> io::Result<File> {
+    match path() {
+        Some(path) => read(&path).map(Option::unwrap_or_default),
+        None => Ok(File::default()),
+    }
+}
+
+/// Reads config file at `path`, `None` if it does not exist.
+pub fn read(path: &Path) -> io::Result<Option<File>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    let invalid = |message: String| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid config file {}: {}", path.display(), message),
+        )
+    };
+    let file: File = serde_json::from_str(&text).map_err(|error| invalid(error.to_string()))?;
+    if file
+        .socket
+        .as_ref()
+        .is_some_and(|socket| socket.is_relative())
+    {
+        return Err(invalid("socket must be absolute".into()));
+    }
+    for (name, preset) in &file.presets {
+        if preset.duration.is_some() && preset.stopwatch {
+            return Err(invalid(format!(
+                "preset {} has both duration and stopwatch",
+                name
+            )));
+        }
+    }
+    for (name, schedule) in &file.schedules {
+        let options = &schedule.options;
+        if options.duration.is_some() && options.stopwatch {
+            return Err(invalid(format!(
+                "schedule {} has both duration and stopwatch",
+                name
+            )));
+        }
+    }
+    Ok(Some(file))
+}
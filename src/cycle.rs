@@ -0,0 +1,72 @@
+use super::duration;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Timer of a cycle, like `work=25m`.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Phase {
+    pub label: String,
+    pub duration: Duration,
+}
+
+/// Phases run one after another under the same timer name.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Cycle {
+    pub phases: Vec<Phase>,
+    /// Current phase.
+    pub index: usize,
+}
+
+/// Where timer is in its cycle, as shown to reports and callbacks.
+#[derive(Deserialize, Serialize, PartialEq, Eq)]
+pub struct Position {
+    pub phase: String,
+    /// Counts from 1.
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Cycle {
+    pub fn phase(&self) -> &Phase {
+        &self.phases[self.index]
+    }
+
+    /// Cycle moved to the next phase, `None` after the last one.
+    pub fn next(&self) -> Option<Cycle> {
+        if self.index + 1 < self.phases.len() {
+            Some(Cycle {
+                phases: self.phases.clone(),
+                index: self.index + 1,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        Position {
+            phase: self.phase().label.clone(),
+            index: self.index + 1,
+            count: self.phases.len(),
+        }
+    }
+}
+
+/// Parses comma separated phases like `work=25m,break=5m`.
+pub fn parse(s: &str) -> Result<Vec<Phase>, String> {
+    s.split(',').map(parse_phase).collect()
+}
+
+/// Parses phase like `long-break=15m`.
+pub fn parse_phase(s: &str) -> Result<Phase, String> {
+    let (label, length) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Phase must look like label=duration: {}", s))?;
+    if label.is_empty() {
+        return Err(format!("Phase has no label: {}", s));
+    }
+    Ok(Phase {
+        label: label.into(),
+        duration: duration::parse(length)?,
+    })
+}
@@ -0,0 +1,302 @@
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::{num::IntErrorKind, time::Duration};
+
+const SECOND: u64 = 1000;
+const MINUTE: u64 = 60 * SECOND;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+/// Parses durations like `90`, `1.5`, `90s`, `250ms`, `25m`, `1h30m`,
+/// `1:30:00` or `PT25M`, down to milliseconds.
+pub fn parse(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+
+    let millis = if s.is_empty() {
+        return Err("Duration is empty".into());
+    } else if s.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        number(s, SECOND)?
+    } else if s.starts_with('P') || s.starts_with('p') {
+        parse_iso(&s[1..])?
+    } else if s.contains(':') {
+        parse_clock(s)?
+    } else {
+        parse_units(
+            s,
+            &[
+                ("d", DAY),
+                ("h", HOUR),
+                ("m", MINUTE),
+                ("s", SECOND),
+                ("ms", 1),
+            ],
+        )?
+    };
+
+    if millis == 0 {
+        Err("Duration cannot be zero".into())
+    } else {
+        Ok(Duration::from_millis(millis))
+    }
+}
+
+/// Formats duration the way `parse` accepts it, like `1h30m`, `45s` or
+/// `1s500ms`.
+pub fn format(duration: Duration) -> String {
+    let millis = duration.as_millis() as u64;
+    if millis == 0 {
+        return "0s".into();
+    }
+
+    let parts = [
+        (millis / DAY, "d"),
+        (millis % DAY / HOUR, "h"),
+        (millis % HOUR / MINUTE, "m"),
+        (millis % MINUTE / SECOND, "s"),
+        (millis % SECOND, "ms"),
+    ];
+
+    parts
+        .iter()
+        .filter(|(qty, _)| *qty != 0)
+        .map(|(qty, unit)| format!("{}{}", qty, unit))
+        .collect()
+}
+
+/// Serde helpers for optional durations written like `25m`, used by config
+/// file and presets.
+pub mod optional {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        d.map(format).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|s| parse(&s))
+            .transpose()
+            .map_err(D::Error::custom)
+    }
+}
+
+fn parse_iso(s: &str) -> Result<u64, String> {
+    let (date, time) = match s.find(['T', 't']) {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => (s, ""),
+    };
+    if date.is_empty() && time.is_empty() {
+        return Err(format!("Invalid ISO-8601 duration: P{}", s));
+    }
+
+    let date = parse_units(date, &[("D", DAY)])?;
+    let time = parse_units(time, &[("H", HOUR), ("M", MINUTE), ("S", SECOND)])?;
+    add(date, time)
+}
+
+fn parse_clock(s: &str) -> Result<u64, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() > 3 {
+        return Err(format!("Invalid clock duration: {}", s));
+    }
+
+    let mut millis: u64 = 0;
+    for (i, part) in parts.iter().enumerate() {
+        // Only seconds can have a fraction.
+        let fraction = i + 1 != parts.len() && part.contains('.');
+        let value = number(part, SECOND)?;
+        if fraction || i != 0 && value >= 60 * SECOND {
+            return Err(format!("Invalid clock duration: {}", s));
+        }
+        millis = mul(millis, 60).and_then(|millis| add(millis, value))?;
+    }
+    Ok(millis)
+}
+
+fn parse_units(s: &str, units: &[(&str, u64)]) -> Result<u64, String> {
+    let mut millis: u64 = 0;
+    let mut rest = s;
+    let mut allowed = units;
+
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or_else(|| format!("Missing unit in duration: {}", s))?;
+        let (digits, tail) = rest.split_at(split);
+        let length = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(length);
+
+        let position = allowed
+            .iter()
+            .position(|&(u, _)| u.eq_ignore_ascii_case(unit))
+            .ok_or_else(|| format!("Unexpected unit '{}' in duration: {}", unit, s))?;
+        let value = number(digits, allowed[position].1)?;
+
+        millis = add(millis, value)?;
+        allowed = &allowed[position + 1..];
+        rest = tail;
+    }
+    Ok(millis)
+}
+
+/// Parses number like `2` or `1.5` of `unit` milliseconds long.
+fn number(s: &str, unit: u64) -> Result<u64, String> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err("Missing number in duration".into());
+    }
+    let parse = |digits: &str| match digits {
+        "" => Ok(0),
+        _ => digits.parse::<u64>().map_err(|e| match e.kind() {
+            IntErrorKind::PosOverflow => "Duration is too long".into(),
+            _ => format!("Invalid number '{}' in duration", s),
+        }),
+    };
+
+    let mut millis = mul(parse(whole)?, unit)?;
+    if !fraction.is_empty() {
+        let scale = 10u64
+            .checked_pow(fraction.len() as u32)
+            .ok_or("Duration is more precise than milliseconds")?;
+        let part = mul(parse(fraction)?, unit)?;
+        if part % scale != 0 {
+            return Err("Duration is more precise than milliseconds".into());
+        }
+        millis = add(millis, part / scale)?;
+    }
+    Ok(millis)
+}
+
+fn add(a: u64, b: u64) -> Result<u64, String> {
+    a.checked_add(b)
+        .ok_or_else(|| "Duration is too long".into())
+}
+
+fn mul(a: u64, b: u64) -> Result<u64, String> {
+    a.checked_mul(b)
+        .ok_or_else(|| "Duration is too long".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(s: &str) -> Result<u64, String> {
+        parse(s).map(|d| d.as_millis() as u64)
+    }
+
+    #[test]
+    fn plain_numbers_are_seconds() {
+        assert_eq!(millis("90"), Ok(90 * SECOND));
+        assert_eq!(millis("1.5"), Ok(1500));
+        assert_eq!(millis(".25"), Ok(250));
+        assert_eq!(millis(" 5 "), Ok(5 * SECOND));
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(millis("90s"), Ok(90 * SECOND));
+        assert_eq!(millis("250ms"), Ok(250));
+        assert_eq!(millis("25m"), Ok(25 * MINUTE));
+        assert_eq!(millis("1h30m"), Ok(HOUR + 30 * MINUTE));
+        assert_eq!(
+            millis("1d2h3m4s5ms"),
+            Ok(DAY + 2 * HOUR + 3 * MINUTE + 4 * SECOND + 5)
+        );
+        assert_eq!(millis("1H30M"), Ok(HOUR + 30 * MINUTE));
+        assert_eq!(millis("1.5h"), Ok(HOUR + 30 * MINUTE));
+        assert_eq!(millis("2.5s"), Ok(2500));
+    }
+
+    #[test]
+    fn clock() {
+        assert_eq!(millis("1:30:00"), Ok(HOUR + 30 * MINUTE));
+        assert_eq!(millis("1:30"), Ok(MINUTE + 30 * SECOND));
+        assert_eq!(millis("90:00"), Ok(90 * MINUTE));
+        assert_eq!(millis("0:00:01.5"), Ok(1500));
+    }
+
+    #[test]
+    fn iso() {
+        assert_eq!(millis("PT25M"), Ok(25 * MINUTE));
+        assert_eq!(millis("P1DT1H"), Ok(DAY + HOUR));
+        assert_eq!(millis("P2D"), Ok(2 * DAY));
+        assert_eq!(millis("pt1h30m"), Ok(HOUR + 30 * MINUTE));
+        assert_eq!(millis("PT0.5S"), Ok(500));
+    }
+
+    #[test]
+    fn rejections() {
+        let rejected = [
+            "",
+            "0",
+            "0s",
+            "0:00",
+            "PT0S",
+            "1m1h",
+            "1s1s",
+            "1h30",
+            "1x",
+            "h",
+            "PT",
+            "P",
+            "1.5ms",
+            "0.0001",
+            "1.2.3",
+            "1:60",
+            "1:2:3:4",
+            "1.5:00",
+            "-1",
+            "99999999999999999999",
+            "213503982335d",
+        ];
+        for s in &rejected {
+            assert!(parse(s).is_err(), "{} was accepted", s);
+        }
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(
+            millis("1.2.3"),
+            Err("Invalid number '1.2.3' in duration".into())
+        );
+        assert_eq!(
+            millis("1m1h"),
+            Err("Unexpected unit 'h' in duration: 1m1h".into())
+        );
+        assert_eq!(millis("1h30"), Err("Missing unit in duration: 1h30".into()));
+        assert_eq!(
+            millis("99999999999999999999"),
+            Err("Duration is too long".into())
+        );
+        assert_eq!(
+            millis("1.5ms"),
+            Err("Duration is more precise than milliseconds".into())
+        );
+        assert_eq!(millis("0"), Err("Duration cannot be zero".into()));
+    }
+
+    #[test]
+    fn format_parse_round_trips() {
+        let formatted = [
+            "1d",
+            "1h30m",
+            "25m",
+            "45s",
+            "1s500ms",
+            "250ms",
+            "2d3h4m5s6ms",
+        ];
+        for s in &formatted {
+            assert_eq!(format(parse(s).unwrap()), *s);
+        }
+
+        for &millis in &[1, 999, SECOND, 90 * SECOND, HOUR + 1, 3 * DAY + 5 * MINUTE] {
+            let duration = Duration::from_millis(millis);
+            assert_eq!(parse(&format(duration)), Ok(duration));
+        }
+        assert_eq!(format(Duration::ZERO), "0s");
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// Commands run on specific timer events.
+pub type Hooks = HashMap<Event, String>;
+
+#[derive(Deserialize, Serialize, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+pub enum Event {
+    Start,
+    Step,
+    Pause,
+    Resume,
+    Finish,
+    Halt,
+    /// System clock was set or system woke up from suspend.
+    Jump,
+    /// Timer was extended, shortened, renamed or otherwise changed.
+    Change,
+    /// Timer finished late, like when system slept through its end.  Only
+    /// hooks run on it, along with finish ones.
+    Missed,
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Start => write!(f, "start"),
+            Self::Step => write!(f, "step"),
+            Self::Pause => write!(f, "pause"),
+            Self::Resume => write!(f, "resume"),
+            Self::Finish => write!(f, "finish"),
+            Self::Halt => write!(f, "halt"),
+            Self::Jump => write!(f, "jump"),
+            Self::Change => write!(f, "change"),
+            Self::Missed => write!(f, "missed"),
+        }
+    }
+}
+
+impl FromStr for Event {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "start" => Ok(Self::Start),
+            "step" => Ok(Self::Step),
+            "pause" => Ok(Self::Pause),
+            "resume" => Ok(Self::Resume),
+            "finish" => Ok(Self::Finish),
+            "halt" => Ok(Self::Halt),
+            "jump" => Ok(Self::Jump),
+            "change" => Ok(Self::Change),
+            "missed" => Ok(Self::Missed),
+            _ => Err(format!("Unknown event: {}", s)),
+        }
+    }
+}
+
+/// Serde helpers for hooks keyed by event name, like `"finish": "play-sound"`.
+pub mod by_name {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(hooks: &Hooks, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(hooks.iter().map(|(event, hook)| (event.to_string(), hook)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Hooks, D::Error> {
+        HashMap::<String, String>::deserialize(d)?
+            .into_iter()
+            .map(|(event, hook)| Ok((event.parse().map_err(D::Error::custom)?, hook)))
+            .collect()
+    }
+}
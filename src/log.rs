@@ -0,0 +1,43 @@
+use std::{
+    fmt,
+    io::{stderr, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+macro_rules! error {
+    ($($arg:tt)*) => { $crate::log::write("ERROR", format_args!($($arg)*)) };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => { $crate::log::write("WARN", format_args!($($arg)*)) };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::log::write("INFO", format_args!($($arg)*)) };
+}
+
+/// Writes timestamped diagnostics line to stderr, which is the log file when
+/// server runs as a daemon.
+pub fn write(level: &str, args: fmt::Arguments) {
+    let _ = writeln!(stderr(), "{} {:5} {}", timestamp(), level, args);
+}
+
+fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs() as libc::time_t;
+
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&secs, &mut tm) };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
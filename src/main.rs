@@ -1,7 +1,17 @@
+#[macro_use]
+mod log;
+
+mod chain;
 mod client;
+mod clock;
 mod config;
+mod cycle;
+mod duration;
+mod event;
+mod preset;
 mod request;
 mod response;
+mod schedule;
 mod server;
 mod snapshot;
 mod socket;
@@ -10,16 +20,26 @@ mod state;
 use config::Config;
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("{}", e);
-        std::process::exit(1);
+    match run() {
+        Ok(status) => std::process::exit(status),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
     }
 }
 
-fn run() -> Result<(), Box<dyn std::error::Error>> {
-    match Config::new() {
-        Config::Server { callback } => server::run(callback)?,
-        Config::Client { request } => client::run(request)?,
-    }
-    Ok(())
+fn run() -> Result<i32, Box<dyn std::error::Error>> {
+    let status = match Config::new()? {
+        Config::Server { options } => server::run(options)?,
+        Config::Client { request, socket } => {
+            client::run(request, &socket)?;
+            0
+        }
+        Config::Check => {
+            config::check()?;
+            0
+        }
+    };
+    Ok(status)
 }
@@ -0,0 +1,116 @@
+use super::{
+    chain::Count,
+    clock::Clock,
+    duration,
+    event::{self, Hooks},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    time::Duration,
+};
+
+/// Presets by their names.
+pub type Presets = BTreeMap<String, Preset>;
+
+/// Named set of timer options expanded by `rimer add --preset`.  Every field
+/// is optional and is overridden by the ones given to `add`.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    /// Timer name, defaults to preset name.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, with = "duration::optional")]
+    pub duration: Option<Duration>,
+    #[serde(default)]
+    pub stopwatch: bool,
+    /// Keep counting after duration until halted.
+    #[serde(default)]
+    pub overtime: bool,
+    #[serde(default, with = "duration::optional")]
+    pub step: Option<Duration>,
+    #[serde(default)]
+    pub clock: Option<Clock>,
+    /// How many times timer runs.
+    #[serde(default)]
+    pub repeat: Option<Count>,
+    /// Pause between runs of repeating timer.
+    #[serde(default, with = "duration::optional")]
+    pub gap: Option<Duration>,
+    #[serde(default)]
+    pub arg: Option<String>,
+    #[serde(default)]
+    pub callback: Option<String>,
+    #[serde(default, with = "event::by_name")]
+    pub hooks: Hooks,
+}
+
+impl Preset {
+    /// Fills fields missing from `self` with the ones of `base`.
+    pub fn over(self, base: &Preset) -> Preset {
+        let (duration, stopwatch) = if self.duration.is_some() || self.stopwatch {
+            (self.duration, self.stopwatch)
+        } else {
+            (base.duration, base.stopwatch)
+        };
+        let mut hooks = base.hooks.clone();
+        hooks.extend(self.hooks);
+
+        Preset {
+            name: self.name.or_else(|| base.name.clone()),
+            duration,
+            stopwatch,
+            overtime: self.overtime || base.overtime,
+            step: self.step.or(base.step),
+            clock: self.clock.or(base.clock),
+            repeat: self.repeat.or(base.repeat),
+            gap: self.gap.or(base.gap),
+            arg: self.arg.or_else(|| base.arg.clone()),
+            callback: self.callback.or_else(|| base.callback.clone()),
+            hooks,
+        }
+    }
+}
+
+impl Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut fields = Vec::new();
+        if let Some(name) = &self.name {
+            fields.push(format!("name {}", name));
+        }
+        match self.duration {
+            Some(duration) => fields.push(format!("duration {}", duration::format(duration))),
+            None if self.stopwatch => fields.push("stopwatch".into()),
+            None => (),
+        }
+        if self.overtime {
+            fields.push("overtime".into());
+        }
+        if let Some(step) = self.step {
+            fields.push(format!("step {}", duration::format(step)));
+        }
+        if let Some(clock) = self.clock {
+            fields.push(format!("clock {}", clock));
+        }
+        if let Some(repeat) = self.repeat {
+            fields.push(format!("repeat {}", repeat));
+        }
+        if let Some(gap) = self.gap {
+            fields.push(format!("gap {}", duration::format(gap)));
+        }
+        if let Some(arg) = &self.arg {
+            fields.push(format!("arg {}", arg));
+        }
+        if let Some(callback) = &self.callback {
+            fields.push(format!("callback {}", callback));
+        }
+        let mut hooks: Vec<_> = self.hooks.iter().collect();
+        hooks.sort();
+        for (event, hook) in hooks {
+            fields.push(format!("on-{} {}", event, hook));
+        }
+        write!(f, "{}", fields.join(", "))
+    }
+}
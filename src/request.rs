@@ -1,13 +1,25 @@
+use super::{
+    chain::{Count, Link},
+    clock::Clock,
+    cycle::Phase,
+    event::Hooks,
+    preset::Preset,
+    schedule::Schedule,
+};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 #[derive(Deserialize, Serialize)]
 pub enum Request {
+    /// Timer `options` given on command line override fields of `preset`.
+    /// Timers of `then` start one after another once it finishes, the whole
+    /// chain runs `rounds` times.  Alarm runs until wall-clock time `at`.
     Add {
-        name: String,
-        duration: Duration,
-        step: Duration,
-        arg: String,
+        preset: Option<String>,
+        options: Preset,
+        at: Option<SystemTime>,
+        then: Vec<Link>,
+        rounds: Count,
     },
     Pause {
         name: String,
@@ -18,8 +30,79 @@ pub enum Request {
     Resume {
         name: String,
     },
+    /// Runs `phases` one after another as timer `name`.
+    Cycle {
+        name: String,
+        phases: Vec<Phase>,
+        step: Option<Duration>,
+        clock: Option<Clock>,
+        arg: Option<String>,
+        callback: Option<String>,
+        hooks: Hooks,
+    },
+    /// Gives running timer more time.
+    Extend {
+        name: String,
+        by: Duration,
+    },
+    Shorten {
+        name: String,
+        by: Duration,
+    },
+    /// Replaces given options of running timer.
+    Set {
+        name: String,
+        duration: Option<Duration>,
+        step: Option<Duration>,
+        arg: Option<String>,
+    },
+    Rename {
+        from: String,
+        to: String,
+    },
+    /// Halts timer and starts the next phase of its cycle.
+    Skip {
+        name: String,
+    },
+    /// Starts timer over from zero.
+    Restart {
+        name: String,
+    },
     Report {
         json: bool,
+        human: bool,
+        millis: bool,
+    },
+    Status,
+    /// Registers preset until server quits, replacing one with same name.
+    Preset {
+        name: String,
+        preset: Preset,
+    },
+    Presets {
+        json: bool,
+    },
+    /// Registers schedule, replacing one with same name.  Unlike presets,
+    /// schedules are kept for the next start.
+    Schedule {
+        name: String,
+        schedule: Schedule,
+    },
+    Unschedule {
+        name: String,
+    },
+    Schedules {
+        json: bool,
+    },
+    Reload {
+        callback: Option<String>,
+        hooks: Hooks,
     },
     Quit,
+    /// Sent by server to itself when it catches a termination signal.
+    Signal {
+        signal: i32,
+    },
+    /// Sent by server to itself when timer is over, see `server::waker`.
+    Wake,
 }
@@ -1,17 +1,26 @@
-use super::snapshot::Snapshot;
+use super::{preset::Presets, schedule::Schedules, snapshot::Snapshot};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashSet,
     fmt::{self, Display},
 };
 
-pub type Response = Result<Option<HashSet<Snapshot>>, Error>;
+pub type Response = Result<Option<Reply>, Error>;
+
+#[derive(Deserialize, Serialize)]
+pub enum Reply {
+    Report(HashSet<Snapshot>),
+    Presets(Presets),
+    Schedules(Schedules),
+}
 
 #[derive(Deserialize, Serialize)]
 pub enum Error {
     NameExists,
     NameNotExists,
     InvalidDuration,
+    PresetNotExists,
+    ScheduleNotExists,
     Generic { message: String },
 }
 
@@ -27,6 +36,8 @@ impl Display for Error {
             Self::InvalidDuration => {
                 write!(f, "Provided duration is zero or bigger than max u64")
             }
+            Self::PresetNotExists => write!(f, "Preset with this name does not exist"),
+            Self::ScheduleNotExists => write!(f, "Schedule with this name does not exist"),
             Self::Generic { message } => write!(f, "{}", message),
         }
     }
@@ -0,0 +1,242 @@
+use super::{
+    clock::{self, Time},
+    preset::Preset,
+};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    time::SystemTime,
+};
+
+/// Schedules by their names.
+pub type Schedules = BTreeMap<String, Schedule>;
+
+/// Timer started by server whenever it is due, like `rimer add` run by cron.
+/// Timer is named after schedule unless its options say otherwise.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    pub every: Every,
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub options: Preset,
+}
+
+/// When schedule is due, written like `weekdays 09:00`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Every {
+    pub days: Days,
+    pub time: Time,
+}
+
+/// Days of week as bits, Sunday is bit 0 like in `libc::tm`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Days(u8);
+
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl Days {
+    pub const DAILY: Days = Days(0b111_1111);
+    pub const WEEKDAYS: Days = Days(0b011_1110);
+    pub const WEEKENDS: Days = Days(0b100_0001);
+
+    pub fn contains(self, day: i32) -> bool {
+        self.0 & 1 << day != 0
+    }
+
+    /// Parses `daily`, `weekdays`, `weekends` or comma separated days and
+    /// ranges like `mon,wed-fri`.
+    pub fn parse(s: &str) -> Result<Days, String> {
+        match s {
+            "daily" => return Ok(Self::DAILY),
+            "weekdays" => return Ok(Self::WEEKDAYS),
+            "weekends" => return Ok(Self::WEEKENDS),
+            _ => (),
+        }
+
+        let day = |name: &str| {
+            DAY_NAMES
+                .iter()
+                .position(|&day| day == name)
+                .ok_or_else(|| format!("Unknown day {}, expected one of mon..sun", name))
+        };
+        let mut bits = 0;
+        for part in s.split(',') {
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (day(first)?, day(last)?),
+                None => (day(part)?, day(part)?),
+            };
+            // Ranges like fri-mon go over the weekend.
+            let mut day = first;
+            loop {
+                bits |= 1 << day;
+                if day == last {
+                    break;
+                }
+                day = (day + 1) % 7;
+            }
+        }
+        Ok(Days(bits))
+    }
+}
+
+impl Display for Days {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::DAILY => write!(f, "daily"),
+            Self::WEEKDAYS => write!(f, "weekdays"),
+            Self::WEEKENDS => write!(f, "weekends"),
+            days => {
+                // Monday first.
+                let names: Vec<_> = (1..=7)
+                    .map(|day| day % 7)
+                    .filter(|&day| days.contains(day))
+                    .map(|day| DAY_NAMES[day as usize])
+                    .collect();
+                write!(f, "{}", names.join(","))
+            }
+        }
+    }
+}
+
+impl Every {
+    /// Parses time optionally preceded by days, like `09:00` or
+    /// `mon-fri 09:00`.  Time alone means every day.
+    pub fn parse(s: &str) -> Result<Every, String> {
+        match s.split_whitespace().collect::<Vec<_>>()[..] {
+            [time] => Ok(Every {
+                days: Days::DAILY,
+                time: Time::parse(time)?,
+            }),
+            [days, time] => Ok(Every {
+                days: Days::parse(days)?,
+                time: Time::parse(time)?,
+            }),
+            _ => Err(format!("Expected days and time like weekdays 09:00: {}", s)),
+        }
+    }
+
+    /// First time schedule is due after `after`.
+    pub fn next(self, after: SystemTime) -> Option<SystemTime> {
+        clock::next(self.time, |day| self.days.contains(day), after)
+    }
+}
+
+impl Display for Every {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.days, self.time)
+    }
+}
+
+impl Serialize for Every {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Every {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        Every::parse(&String::deserialize(d)?).map_err(D::Error::custom)
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.every)?;
+        if let Some(preset) = &self.preset {
+            write!(f, ", preset {}", preset)?;
+        }
+        let options = self.options.to_string();
+        if !options.is_empty() {
+            write!(f, ", {}", options)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn days(s: &str) -> String {
+        Days::parse(s).unwrap().to_string()
+    }
+
+    #[test]
+    fn days_parse_display_round_trips() {
+        assert_eq!(days("daily"), "daily");
+        assert_eq!(days("weekdays"), "weekdays");
+        assert_eq!(days("weekends"), "weekends");
+        assert_eq!(days("mon-fri"), "weekdays");
+        assert_eq!(days("sat,sun"), "weekends");
+        assert_eq!(days("sun-sat"), "daily");
+        assert_eq!(days("wed"), "wed");
+        assert_eq!(days("wed-wed"), "wed");
+        assert_eq!(days("sun,mon"), "mon,sun");
+        assert_eq!(days("mon,wed-fri"), "mon,wed,thu,fri");
+        assert_eq!(days("mon,mon-tue"), "mon,tue");
+
+        for s in &["daily", "weekdays", "mon,wed,thu,fri", "tue,sat", "sun"] {
+            assert_eq!(days(&days(s)), days(s));
+        }
+    }
+
+    #[test]
+    fn day_ranges_wrap_over_weekend() {
+        assert_eq!(days("fri-mon"), "mon,fri,sat,sun");
+        assert_eq!(days("sat-sun"), "weekends");
+        assert_eq!(days("sun-sun"), "sun");
+        assert_eq!(days("tue-mon"), "daily");
+    }
+
+    #[test]
+    fn day_bits_follow_tm_wday() {
+        let fri_mon = Days::parse("fri-mon").unwrap();
+        let wdays: Vec<_> = (0..7).filter(|&day| fri_mon.contains(day)).collect();
+        assert_eq!(wdays, [0, 1, 5, 6]);
+    }
+
+    #[test]
+    fn invalid_days() {
+        for s in &[
+            "",
+            "funday",
+            "Mon",
+            "mon-",
+            "-mon",
+            "mon,,tue",
+            "mon-tue-wed",
+            "monday",
+        ] {
+            assert!(Days::parse(s).is_err(), "{} was accepted", s);
+        }
+    }
+
+    #[test]
+    fn every_parse_display_round_trips() {
+        let every = |s: &str| Every::parse(s).unwrap().to_string();
+
+        assert_eq!(every("09:00"), "daily 09:00");
+        assert_eq!(every("weekdays 9:30:15"), "weekdays 09:30:15");
+        assert_eq!(every("  fri-mon   18:00 "), "mon,fri,sat,sun 18:00");
+        assert_eq!(every(&every("tue,thu 07:45")), "tue,thu 07:45");
+
+        for s in &["", "mon", "mon tue 09:00", "mon 25:00", "09:00 mon"] {
+            assert!(Every::parse(s).is_err(), "{} was accepted", s);
+        }
+    }
+
+    #[test]
+    fn every_next_skips_other_days() {
+        // Wednesday.
+        let after = clock::parse("2026-10-14T12:00").unwrap();
+        let next = |s: &str| clock::format(Every::parse(s).unwrap().next(after).unwrap());
+
+        assert_eq!(next("fri-mon 09:00"), "2026-10-16 09:00");
+        assert_eq!(next("weekends 09:00"), "2026-10-17 09:00");
+        assert_eq!(next("weekdays 18:00"), "2026-10-14 18:00");
+        assert_eq!(next("wed 08:00"), "2026-10-21 08:00");
+    }
+}
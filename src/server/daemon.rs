@@ -0,0 +1,45 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    os::unix::io::AsRawFd,
+    path::Path,
+    process,
+};
+
+/// Detaches server from terminal.  Parent process exits right away, child
+/// continues in a new session with stdout and stderr appended to `log`.
+///
+/// Must be called before any thread is spawned.
+pub fn daemonize(log: &Path) -> io::Result<()> {
+    let log = OpenOptions::new().create(true).append(true).open(log)?;
+    let null = File::open("/dev/null")?;
+
+    match unsafe { libc::fork() } {
+        -1 => return Err(io::Error::last_os_error()),
+        0 => (),
+        _ => unsafe { libc::_exit(0) },
+    }
+
+    if unsafe { libc::setsid() } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    for &(from, to) in &[
+        (null.as_raw_fd(), libc::STDIN_FILENO),
+        (log.as_raw_fd(), libc::STDOUT_FILENO),
+        (log.as_raw_fd(), libc::STDERR_FILENO),
+    ] {
+        if unsafe { libc::dup2(from, to) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+pub fn write_pid(path: &Path) -> io::Result<()> {
+    fs::write(path, format!("{}\n", process::id()))
+}
+
+pub fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,69 @@
+use crate::{clock::Clock, duration};
+use std::{
+    sync::mpsc::Sender,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Clocks drifting apart by less than this are not worth a callback.
+const THRESHOLD: Duration = Duration::from_secs(3);
+
+/// Clock readings taken at the same moment.
+struct Readings {
+    system: SystemTime,
+    wall: Duration,
+    active: Duration,
+}
+
+impl Readings {
+    fn now() -> Self {
+        Readings {
+            system: SystemTime::now(),
+            wall: Clock::Wall.now(),
+            active: Clock::Active.now(),
+        }
+    }
+}
+
+/// Watches for system clock being set and for system waking up from suspend,
+/// and sends to `queue` when either happens.  Timers do not move on their
+/// own then, since they wait on monotonic clock, but their callbacks should
+/// know right away.
+pub fn spawn(queue: Sender<()>) {
+    thread::Builder::new()
+        .name("jumps".into())
+        .spawn(move || {
+            let mut last = Readings::now();
+            loop {
+                thread::sleep(INTERVAL);
+                let now = Readings::now();
+                let wall = now.wall - last.wall;
+                let active = now.active - last.active;
+
+                let mut jumped = false;
+                let suspend = wall.saturating_sub(active);
+                if suspend >= THRESHOLD {
+                    info!("Woke up after {} of suspend", duration::format(suspend));
+                    jumped = true;
+                }
+                let (system, forward) = match now.system.duration_since(last.system) {
+                    Ok(system) if system >= wall => (system - wall, true),
+                    Ok(system) => (wall - system, false),
+                    Err(error) => (error.duration() + wall, false),
+                };
+                if system >= THRESHOLD {
+                    let direction = if forward { "forward" } else { "back" };
+                    info!("Clock was set {} {}", duration::format(system), direction);
+                    jumped = true;
+                }
+
+                if jumped && queue.send(()).is_err() {
+                    break;
+                }
+                last = now;
+            }
+        })
+        .unwrap();
+}
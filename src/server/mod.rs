@@ -1,82 +1,387 @@
+mod daemon;
+mod jumps;
+mod scheduler;
+mod signal;
+mod store;
 mod timer;
 mod updater;
+mod waker;
 
 use crate::{
-    request::Request, response::Error, response::Response, snapshot as s, socket::listener,
+    chain::{Chain, Link},
+    clock::Clock,
+    config::file::{self, File},
+    cycle::Cycle,
+    event::Hooks,
+    preset::{Preset, Presets},
+    request::Request,
+    response::{Error, Reply, Response},
+    schedule::{Schedule, Schedules},
+    snapshot as s,
+    socket::{self, listener},
+    state,
 };
+use scheduler::Scheduler;
+use state::State;
 use std::{
     collections::{HashMap, HashSet},
     fmt::Display,
-    io::{Read, Write},
+    fs,
+    io::{self, Read, Write},
+    net::Shutdown,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    process,
     sync::{
-        mpsc::{channel, Receiver, Sender, TryRecvError},
+        mpsc::{channel, Receiver, TryRecvError},
         Arc,
     },
+    time::{Duration, SystemTime},
 };
-use timer::Timer;
+use store::Store;
+use timer::{Change, Queues, Spec, Timer};
 use updater::{self as u, Updater};
 
 type Timers = HashMap<Arc<String>, Timer>;
 
+/// What to do with timer once it is over besides freeing it.
+enum FollowUp {
+    /// Start the next phase of cycle even though timer was halted.
+    Skip,
+    /// Start the same timer over.
+    Restart,
+}
+
+/// Step of timers that get it neither from `add`, preset nor config file.
+const DEFAULT_STEP: Duration = Duration::from_secs(10);
+
+/// Server settings given on `rimer start`.
+pub struct Options {
+    /// Callback and hooks given on command line override config file ones.
+    pub callback: Option<String>,
+    pub hooks: Hooks,
+    /// Clock of timers that get it neither from `add`, preset nor config file.
+    pub clock: Option<Clock>,
+    pub timeout: Duration,
+    pub jobs: usize,
+    pub json: bool,
+    pub millis: bool,
+    pub socket: PathBuf,
+    pub replace: bool,
+    pub daemon: bool,
+    /// Log file used by daemon, defaults to `<socket name>.log` in state dir.
+    pub log: Option<PathBuf>,
+    /// Halt timers on quit instead of keeping them for the next start.
+    pub halt_on_exit: bool,
+}
+
 struct Inner {
+    /// Callback and hooks given on start or reload, see `Options`.
+    callback: Option<String>,
+    hooks: Hooks,
+    clock: Option<Clock>,
+    /// Config file as of start or last reload.
+    config: File,
+    /// Presets registered at runtime, they shadow config file ones.
+    presets: Presets,
+    /// Set by commands that halt timer to do something else with it.
+    follow_ups: HashMap<Arc<String>, FollowUp>,
+    /// Schedules registered at runtime, they shadow config file ones.
+    schedules: Schedules,
+    schedules_path: PathBuf,
+    scheduler: Scheduler,
+    due_recv: Receiver<String>,
+    jump_recv: Receiver<()>,
     updater: Updater,
+    store: Store,
     timers: Timers,
-    halt_queue: Sender<Arc<String>>,
-    halt_recv: Receiver<Arc<String>>,
-    report_queue: Sender<u::Snapshot>,
+    queues: Queues,
+    halt_recv: Receiver<(Spec, State)>,
     report_recv: Receiver<u::Snapshot>,
 }
 
-pub fn run(command: String) -> Result<(), Box<dyn std::error::Error>> {
-    let (halt_queue, halt_recv) = channel();
+/// Runs server until it is asked to quit, returns process exit status.
+pub fn run(options: Options) -> Result<i32, Box<dyn std::error::Error>> {
+    let config = file::load()?;
+    let (callback, hooks) = settings(&options.callback, &options.hooks, &config)?;
+    let listener = listener(&options.socket, options.replace)?;
+    let socket_ino = fs::metadata(&options.socket)?.ino();
+    let store_path = store::path(&options.socket)?;
+    let records = store::load(&store_path)?;
+    let schedules_path = store::schedules_path(&options.socket)?;
+    let schedules = store::load_schedules(&schedules_path)?;
+
+    if options.daemon {
+        let log = match options.log {
+            Some(log) => log,
+            None => log_path(&options.socket)?,
+        };
+        daemon::daemonize(&log)?;
+    }
+
+    let pid_path = pid_path(&options.socket);
+    daemon::write_pid(&pid_path)?;
+    signal::spawn(options.socket.clone())?;
+    info!("Listening on {}", options.socket.display());
+
+    let (halt_queue, halt_recv) = waker::spawn(options.socket.clone());
+    let (due_queue, due_recv) = waker::spawn(options.socket.clone());
+    let (jump_queue, jump_recv) = waker::spawn(options.socket.clone());
+    jumps::spawn(jump_queue);
     let (report_queue, report_recv) = channel();
+    let updater = Updater::spawn(
+        callback,
+        hooks,
+        u::Options {
+            timeout: options.timeout,
+            jobs: options.jobs,
+            json: options.json,
+            millis: options.millis,
+        },
+    );
+    let store = Store::spawn(store_path);
+    let scheduler = Scheduler::spawn(all_schedules(&config, &schedules), due_queue);
     let mut inner = Inner {
-        updater: Updater::spawn(command),
+        queues: Queues {
+            update: updater.queue.clone(),
+            halt: halt_queue,
+            report: report_queue,
+            store: store.queue.clone(),
+        },
+        callback: options.callback,
+        hooks: options.hooks,
+        clock: options.clock,
+        config,
+        presets: Presets::new(),
+        follow_ups: HashMap::new(),
+        schedules,
+        schedules_path,
+        scheduler,
+        due_recv,
+        jump_recv,
+        updater,
+        store,
         timers: HashMap::new(),
-        halt_queue,
         halt_recv,
-        report_queue,
         report_recv,
     };
 
-    'main: loop {
-        let listener = listener()?;
+    for record in records {
+        let name = Arc::new(record.name.clone());
+        let timer = Timer::restore(record, inner.queues.clone());
+        inner.timers.insert(name, timer);
+    }
 
-        for stream in listener.incoming().filter_map(Result::ok) {
-            let request = match recv(&stream) {
-                Ok(request) => request,
-                Err(error) => {
-                    send_text_error(&stream, error);
-                    continue;
-                }
-            };
+    let mut status = 0;
 
-            free_halted_timers(&mut inner);
+    for stream in listener.incoming().filter_map(Result::ok) {
+        let request = match recv(&stream) {
+            Ok(request) => request,
+            // Client hung up without request, like `socket::listener` probe.
+            Err(error) if error.is_eof() => continue,
+            Err(error) => {
+                send_text_error(&stream, error);
+                continue;
+            }
+        };
 
-            match request {
-                Request::Add { .. } => {
-                    handle_add(request, &stream, &mut inner);
-                }
-                Request::Pause { .. } | Request::Halt { .. } | Request::Resume { .. } => {
-                    handle_cmd(request, &stream, &mut inner)
-                }
-                Request::Report { .. } => handle_report(&stream, &inner),
-                Request::Quit => {
-                    handle_quit(&stream, inner);
-                    break 'main;
+        free_halted_timers(&mut inner);
+        start_due_timers(&mut inner);
+        if inner.jump_recv.try_iter().count() > 0 {
+            inner.timers.values().for_each(Timer::jump);
+        }
+
+        match request {
+            Request::Add { .. } => {
+                handle_add(request, &stream, &mut inner);
+            }
+            Request::Pause { .. } | Request::Halt { .. } | Request::Resume { .. } => {
+                handle_cmd(request, &stream, &mut inner)
+            }
+            Request::Extend { name, by } => {
+                handle_change(name, Change::Extend { by }, &stream, &mut inner)
+            }
+            Request::Shorten { name, by } => {
+                handle_change(name, Change::Shorten { by }, &stream, &mut inner)
+            }
+            Request::Set {
+                name,
+                duration,
+                step,
+                arg,
+            } => {
+                let change = Change::Set {
+                    duration,
+                    step,
+                    arg,
+                };
+                handle_change(name, change, &stream, &mut inner)
+            }
+            Request::Rename { from, to } => {
+                let change = Change::Rename { name: Arc::new(to) };
+                handle_change(from, change, &stream, &mut inner)
+            }
+            Request::Cycle { .. } => handle_cycle(request, &stream, &mut inner),
+            Request::Skip { name } => handle_follow_up(name, FollowUp::Skip, &stream, &mut inner),
+            Request::Restart { name } => {
+                handle_follow_up(name, FollowUp::Restart, &stream, &mut inner)
+            }
+            Request::Wake => send_ok(&stream),
+            Request::Report { .. } => handle_report(&stream, &inner),
+            Request::Status => send_ok(&stream),
+            Request::Preset { name, preset } => {
+                info!("Preset {} is now {}", name, preset);
+                inner.presets.insert(name, preset);
+                send_ok(&stream);
+            }
+            Request::Presets { .. } => {
+                let mut presets = inner.config.presets.clone();
+                presets.extend(inner.presets.clone());
+                send(&stream, Ok(Some(Reply::Presets(presets))));
+            }
+            Request::Schedule { name, schedule } => {
+                handle_schedule(name, schedule, &stream, &mut inner)
+            }
+            Request::Unschedule { name } => handle_unschedule(name, &stream, &mut inner),
+            Request::Schedules { .. } => {
+                let schedules = all_schedules(&inner.config, &inner.schedules);
+                send(&stream, Ok(Some(Reply::Schedules(schedules))));
+            }
+            Request::Reload { callback, hooks } => match reload(&mut inner, callback, hooks) {
+                Ok(()) => send_ok(&stream),
+                Err(error) => send_text_error(&stream, error),
+            },
+            Request::Signal { signal } if signal == libc::SIGHUP => {
+                if let Err(error) = reload(&mut inner, None, Hooks::new()) {
+                    error!("Cannot reload: {}", error);
                 }
+                send_ok(&stream);
+            }
+            Request::Quit => {
+                handle_quit(&stream, inner, options.halt_on_exit);
+                break;
+            }
+            Request::Signal { signal } => {
+                handle_quit(&stream, inner, options.halt_on_exit);
+                status = 128 + signal;
+                break;
             }
         }
     }
+
+    info!("Quit");
+    remove_own_files(&options.socket, socket_ino, &pid_path)?;
+    Ok(status)
+}
+
+/// Removes socket and pidfile unless they belong to a server that replaced
+/// this one already.
+fn remove_own_files(socket: &Path, socket_ino: u64, pid_path: &Path) -> io::Result<()> {
+    if fs::metadata(socket).is_ok_and(|metadata| metadata.ino() == socket_ino) {
+        fs::remove_file(socket)?;
+    }
+    if daemon::read_pid(pid_path) == Some(process::id()) {
+        fs::remove_file(pid_path)?;
+    }
+    Ok(())
+}
+
+/// Pidfile lives beside the socket, like `rimer.pid` for `rimer.socket`.
+pub fn pid_path(socket: &Path) -> PathBuf {
+    socket.with_extension("pid")
+}
+
+pub fn read_pid(socket: &Path) -> Option<u32> {
+    daemon::read_pid(&pid_path(socket))
+}
+
+/// Log of daemon, like `rimer.log` or `rimer-work.log`, see `store::suffix`.
+fn log_path(socket: &Path) -> io::Result<PathBuf> {
+    let suffix = store::suffix(socket)?;
+    Ok(store::dir(socket)?.join(format!("rimer{}.log", suffix)))
+}
+
+/// Server callback and hooks: given ones over config file ones.
+fn settings(
+    callback: &Option<String>,
+    hooks: &Hooks,
+    config: &File,
+) -> io::Result<(String, Hooks)> {
+    let callback = callback
+        .clone()
+        .or_else(|| config.callback.clone())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Callback is given neither on command line nor in config file",
+            )
+        })?;
+    let mut all = config.hooks.clone();
+    all.extend(hooks.iter().map(|(&event, hook)| (event, hook.clone())));
+    Ok((callback, all))
+}
+
+/// Re-reads config file and applies it along with given overrides.
+fn reload(inner: &mut Inner, callback: Option<String>, hooks: Hooks) -> io::Result<()> {
+    let mut overrides = inner.hooks.clone();
+    overrides.extend(hooks);
+    let callback = callback.or_else(|| inner.callback.clone());
+    let config = file::load()?;
+    let settings = settings(&callback, &overrides, &config)?;
+
+    inner.callback = callback;
+    inner.config = config;
+    inner.hooks = overrides;
+    inner.updater.reload(settings.0, settings.1);
+    inner
+        .scheduler
+        .set(all_schedules(&inner.config, &inner.schedules));
+    Ok(())
+}
+
+/// Config file schedules overlaid by runtime ones.
+fn all_schedules(config: &File, schedules: &Schedules) -> Schedules {
+    let mut all = config.schedules.clone();
+    all.extend(schedules.clone());
+    all
+}
+
+/// Sends `request` to the server itself and waits for it to be handled.
+fn notify(sock: &Path, request: &Request) -> io::Result<()> {
+    let stream = socket::stream(sock)?;
+    serde_json::to_writer(&stream, request)?;
+    stream.shutdown(Shutdown::Write)?;
+    io::copy(&mut &stream, &mut io::sink())?;
     Ok(())
 }
 
+/// Frees timers that are over and starts the ones that follow them.
 fn free_halted_timers(inner: &mut Inner) {
     loop {
         match inner.halt_recv.try_recv() {
-            Ok(name) => {
-                let removed = inner.timers.remove(&name);
-                debug_assert!(removed.is_some())
+            Ok((spec, state)) => {
+                let removed = inner.timers.remove(&spec.name);
+                debug_assert!(removed.is_some());
+
+                let follow_up = inner.follow_ups.remove(&spec.name);
+                let next = match (follow_up, state) {
+                    (Some(FollowUp::Restart), _) => Some(spec),
+                    (Some(FollowUp::Skip), _) | (None, State::Finished) => {
+                        next_phase(&spec).or_else(|| next_link(&spec, inner))
+                    }
+                    (None, _) => None,
+                };
+                match next {
+                    Some(spec) if inner.timers.contains_key(&spec.name) => {
+                        error!("Cannot start {}: {}", spec.name, Error::NameExists);
+                    }
+                    Some(spec) => {
+                        let name = Arc::clone(&spec.name);
+                        let timer = Timer::spawn(spec, inner.queues.clone());
+                        inner.timers.insert(name, timer);
+                    }
+                    None => (),
+                }
             }
             Err(TryRecvError::Empty) => break,
             Err(TryRecvError::Disconnected) => unreachable!(),
@@ -84,32 +389,280 @@ fn free_halted_timers(inner: &mut Inner) {
     }
 }
 
+/// Starts timers of schedules that are due.
+fn start_due_timers(inner: &mut Inner) {
+    let names: Vec<_> = inner.due_recv.try_iter().collect();
+    for name in names {
+        let schedule = match inner
+            .schedules
+            .get(&name)
+            .or(inner.config.schedules.get(&name))
+        {
+            Some(schedule) => schedule.clone(),
+            // Removed while it was being started.
+            None => continue,
+        };
+        match schedule_spec(&name, schedule, inner) {
+            Ok(spec) if inner.timers.contains_key(&spec.name) => {
+                error!("Cannot start {}: {}", spec.name, Error::NameExists);
+            }
+            Ok(spec) => {
+                info!("Schedule {} starts {}", name, spec.name);
+                let timer_name = Arc::clone(&spec.name);
+                let timer = Timer::spawn(spec, inner.queues.clone());
+                inner.timers.insert(timer_name, timer);
+            }
+            Err(error) => error!("Cannot start schedule {}: {}", name, error),
+        }
+    }
+}
+
+/// Spec of timer started by schedule `name`.
+fn schedule_spec(name: &str, schedule: Schedule, inner: &Inner) -> Result<Spec, Error> {
+    let mut options = schedule.options;
+    options.name = options.name.or_else(|| Some(name.into()));
+    let spec = make_spec(schedule.preset, options, inner)?;
+    if spec.duration.is_some_and(|d| d.is_zero()) || spec.step.is_zero() {
+        return Err(Error::InvalidDuration);
+    }
+    Ok(spec)
+}
+
+fn next_phase(spec: &Spec) -> Option<Spec> {
+    let cycle = spec.cycle.as_ref()?.next()?;
+    Some(Spec {
+        duration: Some(cycle.phase().duration),
+        cycle: Some(Arc::new(cycle)),
+        ..spec.clone()
+    })
+}
+
+fn next_link(spec: &Spec, inner: &Inner) -> Option<Spec> {
+    let chain = spec.chain.as_ref()?.next()?;
+    let link = chain.link();
+    let next = make_spec(link.preset.clone(), link.options.clone(), inner).and_then(|next| {
+        let next = Spec {
+            chain: Some(Arc::new(chain)),
+            ..next
+        };
+        check_overtime(&next).map(|()| next)
+    });
+    match next {
+        Ok(next) => Some(next),
+        Err(error) => {
+            error!("Cannot start timer after {}: {}", spec.name, error);
+            None
+        }
+    }
+}
+
 fn handle_add<S: Write + Copy>(request: Request, stream: S, inner: &mut Inner) {
-    let (name, duration, step, arg) = match request {
+    let (preset, mut options, at, then, rounds) = match request {
         Request::Add {
+            preset,
+            options,
+            at,
+            then,
+            rounds,
+        } => (preset, options, at, then, rounds),
+        _ => unreachable!(),
+    };
+
+    if let Some(at) = at {
+        match at.duration_since(SystemTime::now()) {
+            Ok(duration) => options.duration = Some(duration),
+            Err(_) => return send_text_error(stream, "Alarm time has already passed"),
+        }
+    }
+    let mut spec = match make_spec(preset.clone(), options.clone(), inner) {
+        Ok(spec) => spec,
+        Err(error) => return send_error(stream, error),
+    };
+    spec.at = at;
+    // Followers are checked now rather than when they are due.
+    let mut followers = Vec::new();
+    for link in &then {
+        match make_spec(link.preset.clone(), link.options.clone(), inner) {
+            Ok(follower) => followers.push(follower),
+            Err(error) => return send_error(stream, error),
+        }
+    }
+    if !then.is_empty() {
+        let mut links = vec![Link { preset, options }];
+        links.extend(then);
+        spec.chain = Some(Arc::new(Chain {
+            links,
+            index: 0,
+            round: 1,
+            rounds,
+        }));
+    }
+    let checked = check_alarm(&spec)
+        .and_then(|()| check_overtime(&spec))
+        .and_then(|()| {
+            followers
+                .into_iter()
+                .enumerate()
+                .try_for_each(|(index, follower)| {
+                    let chain = spec.chain.as_deref().map(|chain| Chain {
+                        index: index + 1,
+                        ..chain.clone()
+                    });
+                    check_overtime(&Spec {
+                        chain: chain.map(Arc::new),
+                        ..follower
+                    })
+                })
+        });
+    if let Err(error) = checked {
+        return send_error(stream, error);
+    }
+    add_timer(spec, stream, inner);
+}
+
+/// Alarm goes off once, its time cannot come again on the next run, so it
+/// neither repeats, whatever gives it `repeat`, nor loops with its chain.
+fn check_alarm(spec: &Spec) -> Result<(), Error> {
+    if spec.at.is_none() {
+        return Ok(());
+    }
+    let message = if spec.repeat.is_some_and(|repeat| repeat.allows(2)) {
+        "Alarm cannot repeat"
+    } else if spec
+        .chain
+        .as_ref()
+        .is_some_and(|chain| chain.rounds.allows(2))
+    {
+        "Alarm cannot loop"
+    } else {
+        return Ok(());
+    };
+    Err(Error::Generic {
+        message: message.into(),
+    })
+}
+
+/// Timer in overtime never finishes, so it can neither repeat, whatever
+/// gives it `repeat`, nor be followed by the rest of its chain.
+fn check_overtime(spec: &Spec) -> Result<(), Error> {
+    if !spec.overtime {
+        return Ok(());
+    }
+    let message = if spec.repeat.is_some_and(|repeat| repeat.allows(2)) {
+        "cannot repeat"
+    } else if spec
+        .chain
+        .as_ref()
+        .is_some_and(|chain| chain.next().is_some())
+    {
+        "cannot be followed by other timers"
+    } else {
+        return Ok(());
+    };
+    Err(Error::Generic {
+        message: format!(
+            "Timer {} keeps counting after its end, it {}",
+            spec.name, message
+        ),
+    })
+}
+
+/// Spec of timer given by `options` over `preset`.
+fn make_spec(preset: Option<String>, options: Preset, inner: &Inner) -> Result<Spec, Error> {
+    let options = match &preset {
+        Some(preset) => match inner
+            .presets
+            .get(preset)
+            .or(inner.config.presets.get(preset))
+        {
+            Some(base) => options.over(base),
+            None => return Err(Error::PresetNotExists),
+        },
+        None => options,
+    };
+    let name = options.name.or(preset).ok_or_else(|| Error::Generic {
+        message: "Timer has no name".into(),
+    })?;
+    let duration = match (options.duration, options.stopwatch) {
+        (Some(duration), _) => Some(duration),
+        (None, true) => None,
+        (None, false) => {
+            return Err(Error::Generic {
+                message: format!("Timer {} has neither duration nor stopwatch", name),
+            })
+        }
+    };
+
+    let spec = Spec {
+        name: Arc::new(name),
+        duration,
+        step: options.step.or(inner.config.step).unwrap_or(DEFAULT_STEP),
+        clock: clock(options.clock, inner),
+        arg: Arc::new(options.arg.unwrap_or_default()),
+        callback: options.callback.map(Arc::new),
+        hooks: Arc::new(options.hooks),
+        cycle: None,
+        chain: None,
+        repeat: options.repeat,
+        gap: options.gap,
+        at: None,
+        overtime: options.overtime,
+    };
+    check_overtime(&spec)?;
+    Ok(spec)
+}
+
+/// Timer clock: given one, then server default, then config file one.
+fn clock(clock: Option<Clock>, inner: &Inner) -> Clock {
+    clock
+        .or(inner.clock)
+        .or(inner.config.clock)
+        .unwrap_or_default()
+}
+
+fn handle_cycle<S: Write + Copy>(request: Request, stream: S, inner: &mut Inner) {
+    let spec = match request {
+        Request::Cycle {
             name,
-            duration,
+            phases,
             step,
+            clock: cycle_clock,
             arg,
-        } => (name, duration, step, arg),
+            callback,
+            hooks,
+        } => {
+            if phases.is_empty() || phases.iter().any(|phase| phase.duration.is_zero()) {
+                return send_error(stream, Error::InvalidDuration);
+            }
+            let cycle = Cycle { phases, index: 0 };
+            Spec {
+                name: Arc::new(name),
+                duration: Some(cycle.phase().duration),
+                step: step.or(inner.config.step).unwrap_or(DEFAULT_STEP),
+                clock: clock(cycle_clock, inner),
+                arg: Arc::new(arg.unwrap_or_default()),
+                callback: callback.map(Arc::new),
+                hooks: Arc::new(hooks),
+                cycle: Some(Arc::new(cycle)),
+                chain: None,
+                repeat: None,
+                gap: None,
+                at: None,
+                overtime: false,
+            }
+        }
         _ => unreachable!(),
     };
+    add_timer(spec, stream, inner);
+}
 
-    if duration.as_secs() == 0 || step.as_secs() == 0 {
+fn add_timer<S: Write + Copy>(spec: Spec, stream: S, inner: &mut Inner) {
+    if spec.duration.is_some_and(|d| d.is_zero()) || spec.step.is_zero() {
         send_error(stream, Error::InvalidDuration);
     } else {
-        let name = Arc::new(name);
-        let arg = Arc::new(arg);
-        if inner.timers.get(&name).is_none() {
-            let timer = Timer::spawn(
-                Arc::clone(&name),
-                duration,
-                step,
-                inner.updater.queue.clone(),
-                inner.halt_queue.clone(),
-                inner.report_queue.clone(),
-                arg,
-            );
+        let name = Arc::clone(&spec.name);
+        if !inner.timers.contains_key(&name) {
+            let timer = Timer::spawn(spec, inner.queues.clone());
             inner.timers.insert(name, timer);
             send_ok(stream);
         } else {
@@ -119,6 +672,7 @@ fn handle_add<S: Write + Copy>(request: Request, stream: S, inner: &mut Inner) {
 }
 
 fn handle_cmd<S: Write + Copy>(request: Request, stream: S, inner: &mut Inner) {
+    let pause = matches!(request, Request::Pause { .. });
     let (name, cmd): (_, fn(&Timer)) = match request {
         Request::Pause { name } => (name, Timer::pause),
         Request::Halt { name } => (name, Timer::halt),
@@ -127,6 +681,9 @@ fn handle_cmd<S: Write + Copy>(request: Request, stream: S, inner: &mut Inner) {
     };
 
     if let Some(timer) = inner.timers.get(&Arc::new(name)) {
+        if pause && timer.is_alarm() {
+            return send_text_error(stream, "Alarm cannot be paused");
+        }
         cmd(timer);
         send_ok(stream);
     } else {
@@ -134,25 +691,138 @@ fn handle_cmd<S: Write + Copy>(request: Request, stream: S, inner: &mut Inner) {
     }
 }
 
+fn handle_schedule<S: Write + Copy>(
+    name: String,
+    schedule: Schedule,
+    stream: S,
+    inner: &mut Inner,
+) {
+    // Timer options are checked now rather than when schedule is due.
+    if let Err(error) = schedule_spec(&name, schedule.clone(), inner) {
+        return send_error(stream, error);
+    }
+    info!("Schedule {} is now {}", name, schedule);
+    inner.schedules.insert(name, schedule);
+    update_schedules(stream, inner);
+}
+
+fn handle_unschedule<S: Write + Copy>(name: String, stream: S, inner: &mut Inner) {
+    if inner.schedules.remove(&name).is_some() {
+        info!("Schedule {} is removed", name);
+        update_schedules(stream, inner);
+    } else if inner.config.schedules.contains_key(&name) {
+        send_text_error(stream, "Schedule is set in config file");
+    } else {
+        send_error(stream, Error::ScheduleNotExists);
+    }
+}
+
+/// Saves runtime schedules and hands all of them to scheduler.
+fn update_schedules<S: Write + Copy>(stream: S, inner: &Inner) {
+    inner
+        .scheduler
+        .set(all_schedules(&inner.config, &inner.schedules));
+    match store::save_schedules(&inner.schedules_path, &inner.schedules) {
+        Ok(()) => send_ok(stream),
+        Err(error) => send_text_error(
+            stream,
+            format!("Cannot write {}: {}", inner.schedules_path.display(), error),
+        ),
+    }
+}
+
+fn handle_change<S: Write + Copy>(name: String, change: Change, stream: S, inner: &mut Inner) {
+    let name = Arc::new(name);
+    let timer = match inner.timers.get(&name) {
+        Some(timer) => timer,
+        None => return send_error(stream, Error::NameNotExists),
+    };
+    let new_name = match &change {
+        Change::Rename { name } if inner.timers.contains_key(name) => {
+            return send_error(stream, Error::NameExists);
+        }
+        Change::Rename { name } => Some(Arc::clone(name)),
+        _ => None,
+    };
+    // Zero durations are checked like in `add_timer`.
+    let zero = match &change {
+        Change::Extend { by } | Change::Shorten { by } => by.is_zero(),
+        Change::Set { duration, step, .. } => {
+            duration.is_some_and(|d| d.is_zero()) || step.is_some_and(|s| s.is_zero())
+        }
+        Change::Rename { .. } => false,
+    };
+    if zero {
+        return send_error(stream, Error::InvalidDuration);
+    }
+
+    if let Err(error) = timer.change(change) {
+        return send_error(stream, error);
+    }
+    if let Some(new_name) = new_name {
+        info!("Timer {} is now {}", name, new_name);
+        let timer = inner.timers.remove(&name).unwrap();
+        inner.timers.insert(Arc::clone(&new_name), timer);
+        if let Some(follow_up) = inner.follow_ups.remove(&name) {
+            inner.follow_ups.insert(new_name, follow_up);
+        }
+    }
+    send_ok(stream);
+}
+
+/// Halts timer and leaves `follow_up` to `free_halted_timers`.
+fn handle_follow_up<S: Write + Copy>(
+    name: String,
+    follow_up: FollowUp,
+    stream: S,
+    inner: &mut Inner,
+) {
+    let name = Arc::new(name);
+    if let Some(timer) = inner.timers.get(&name) {
+        // Alarm time stays the same, there is nothing to start over.
+        if matches!(follow_up, FollowUp::Restart) && timer.is_alarm() {
+            return send_text_error(stream, "Alarm cannot be restarted");
+        }
+        timer.halt();
+        inner.follow_ups.insert(name, follow_up);
+        send_ok(stream);
+    } else {
+        send_error(stream, Error::NameNotExists);
+    }
+}
+
 fn handle_report<S: Write + Copy>(stream: S, inner: &Inner) {
     inner.timers.values().for_each(Timer::report);
     let report = inner
         .report_recv
         .iter()
         .take(inner.timers.len())
-        .filter(|usnapshot| !usnapshot.state.is_halted())
+        // Over timers are about to be freed, their end is told by callbacks.
+        .filter(|usnapshot| !usnapshot.state.is_over())
         .map(s::Snapshot::from)
         .collect();
 
     send_report(stream, report);
 }
 
-fn handle_quit<S: Write + Copy>(stream: S, inner: Inner) {
+fn handle_quit<S: Write + Copy>(stream: S, inner: Inner, halt_on_exit: bool) {
+    inner.scheduler.quit();
+    inner.scheduler.join();
+
     let timers = inner.timers;
-    timers.values().for_each(Timer::halt);
-    timers.values().for_each(Timer::confirm_halt);
+    if halt_on_exit {
+        timers.values().for_each(Timer::halt);
+        timers.values().for_each(Timer::confirm_halt);
+    } else {
+        // Detached timers stay in store without being halted, so neither
+        // halt callbacks nor hooks run for them.
+        timers.values().for_each(Timer::detach);
+    }
     timers.into_iter().for_each(|(_, timer)| Timer::join(timer));
 
+    inner.store.quit();
+    inner.store.join();
+
     inner.updater.quit();
     inner.updater.join();
     send_ok(stream);
@@ -160,7 +830,7 @@ fn handle_quit<S: Write + Copy>(stream: S, inner: Inner) {
 
 fn send<S: Write + Copy>(stream: S, response: Response) {
     if let Err(error) = serde_json::to_writer(stream, &response) {
-        eprintln!("{}", error);
+        error!("Cannot send response: {}", error);
     }
 }
 
@@ -169,7 +839,7 @@ fn send_ok<S: Write + Copy>(stream: S) {
 }
 
 fn send_report<S: Write + Copy>(stream: S, report: HashSet<s::Snapshot>) {
-    send(stream, Ok(Some(report)))
+    send(stream, Ok(Some(Reply::Report(report))))
 }
 
 fn send_error<S: Write + Copy>(stream: S, error: Error) {
@@ -197,6 +867,12 @@ impl From<u::Snapshot> for s::Snapshot {
             elapsed: source.elapsed,
             state: source.state,
             arg: source.arg.to_string(),
+            cycle: source.cycle.map(|cycle| cycle.position()),
+            iteration: source.iteration,
+            at: source.at,
+            overdue: source.overdue,
+            elapsed_ms: source.elapsed.as_millis() as u64,
+            duration_ms: source.duration.map(|duration| duration.as_millis() as u64),
         }
     }
 }
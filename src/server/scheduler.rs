@@ -0,0 +1,84 @@
+use crate::schedule::Schedules;
+use std::{
+    sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Wall clock may change while waiting, like after suspend or time sync, so
+/// schedules are looked at again at least this often.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct Scheduler {
+    pub handle: thread::JoinHandle<()>,
+    pub queue: Sender<Message>,
+}
+
+pub enum Message {
+    Set { schedules: Schedules },
+    Quit,
+}
+
+impl Scheduler {
+    /// Sends names of schedules to `due` whenever they are due.
+    pub fn spawn(schedules: Schedules, due: Sender<String>) -> Self {
+        let (queue, receiver) = channel();
+
+        let handle = thread::Builder::new()
+            .name("scheduler".into())
+            .spawn(|| run(schedules, receiver, due))
+            .unwrap();
+
+        Scheduler { queue, handle }
+    }
+
+    /// Replaces all schedules.
+    pub fn set(&self, schedules: Schedules) {
+        self.queue.send(Message::Set { schedules }).unwrap();
+    }
+
+    pub fn quit(&self) {
+        self.queue.send(Message::Quit).unwrap();
+    }
+
+    pub fn join(self) {
+        self.handle.join().unwrap();
+    }
+}
+
+fn run(mut schedules: Schedules, receiver: Receiver<Message>, due: Sender<String>) {
+    // Schedules are due at most once for every time they come after this.
+    let mut after = SystemTime::now();
+
+    loop {
+        let timeout = schedules
+            .values()
+            .filter_map(|schedule| schedule.every.next(after))
+            .min()
+            .map_or(RECHECK_INTERVAL, |next| {
+                next.duration_since(SystemTime::now())
+                    .unwrap_or_default()
+                    .min(RECHECK_INTERVAL)
+            });
+
+        match receiver.recv_timeout(timeout) {
+            Ok(Message::Set { schedules: new }) => {
+                schedules = new;
+                after = SystemTime::now();
+            }
+            Ok(Message::Quit) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                let now = SystemTime::now();
+                for (name, schedule) in &schedules {
+                    if schedule.every.next(after).is_some_and(|next| next <= now)
+                        && due.send(name.clone()).is_err()
+                    {
+                        return;
+                    }
+                }
+                // Clock turned back must not make schedules due again.
+                after = after.max(now);
+            }
+        }
+    }
+}
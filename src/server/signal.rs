@@ -0,0 +1,65 @@
+use super::notify;
+use crate::request::Request;
+use std::{
+    fs::File,
+    io::{self, Read},
+    os::unix::io::FromRawFd,
+    path::PathBuf,
+    sync::atomic::{AtomicI32, Ordering},
+    thread,
+};
+
+const SIGNALS: [libc::c_int; 3] = [libc::SIGTERM, libc::SIGINT, libc::SIGHUP];
+
+/// Write end of the pipe that signal handler reports signals to.
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn handler(signal: libc::c_int) {
+    let byte = signal as u8;
+    unsafe {
+        libc::write(
+            PIPE.load(Ordering::Relaxed),
+            &byte as *const u8 as *const _,
+            1,
+        )
+    };
+}
+
+/// Catches signals and turns them into `Request::Signal` sent to the server
+/// itself.  `SIGHUP` reloads server, others go through the same orderly
+/// shutdown as `Request::Quit`.
+pub fn spawn(sock: PathBuf) -> io::Result<()> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    for &fd in &fds {
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    PIPE.store(fds[1], Ordering::Relaxed);
+    let mut pipe = unsafe { File::from_raw_fd(fds[0]) };
+
+    for &signal in &SIGNALS {
+        let handler = handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        if unsafe { libc::signal(signal, handler) } == libc::SIG_ERR {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    thread::Builder::new()
+        .name("signal".into())
+        .spawn(move || {
+            let mut byte = [0];
+            while pipe.read_exact(&mut byte).is_ok() {
+                let signal = i32::from(byte[0]);
+                info!("Caught signal {}", signal);
+                if let Err(error) = notify(&sock, &Request::Signal { signal }) {
+                    error!("Cannot shut down on signal {}: {}", signal, error);
+                }
+            }
+        })?;
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use crate::{
+    chain::{Chain, Count},
+    clock::Clock,
+    cycle::Cycle,
+    event::Hooks,
+    schedule::Schedules,
+    socket,
+    state::State,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, DirBuilder, File},
+    io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+pub struct Store {
+    pub handle: thread::JoinHandle<()>,
+    pub queue: Sender<Message>,
+}
+
+pub enum Message {
+    Save { record: Box<Record> },
+    Remove { name: Arc<String> },
+    Quit,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Record {
+    pub name: String,
+    pub duration: Option<Duration>,
+    pub step: Duration,
+    pub arg: String,
+    pub state: State,
+    pub elapsed: Duration,
+    pub start_time: SystemTime,
+    #[serde(default)]
+    pub callback: Option<String>,
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub cycle: Option<Cycle>,
+    #[serde(default)]
+    pub chain: Option<Chain>,
+    #[serde(default)]
+    pub repeat: Option<Count>,
+    #[serde(default)]
+    pub gap: Option<Duration>,
+    #[serde(default)]
+    pub iteration: u32,
+    #[serde(default)]
+    pub clock: Clock,
+    #[serde(default)]
+    pub overtime: bool,
+    #[serde(default)]
+    pub at: Option<SystemTime>,
+    /// Rest of the gap of repeating timer waiting for its next run.
+    #[serde(default)]
+    pub gap_left: Option<Duration>,
+}
+
+impl Store {
+    pub fn spawn(path: PathBuf) -> Self {
+        let (queue, receiver) = channel();
+
+        let handle = thread::Builder::new()
+            .name("store".into())
+            .spawn(|| run(path, receiver))
+            .unwrap();
+
+        Store { queue, handle }
+    }
+
+    pub fn quit(&self) {
+        self.queue.send(Message::Quit).unwrap();
+    }
+
+    pub fn join(self) {
+        self.handle.join().unwrap();
+    }
+}
+
+pub fn save(queue: &Sender<Message>, record: Record) {
+    // Store quits after timers are joined on shutdown, anything sent after
+    // that is simply dropped.
+    let _ = queue.send(Message::Save {
+        record: Box::new(record),
+    });
+}
+
+pub fn remove(queue: &Sender<Message>, name: Arc<String>) {
+    let _ = queue.send(Message::Remove { name });
+}
+
+/// State directory, `$XDG_STATE_HOME/rimer` or `~/.local/state/rimer`.
+pub fn dir(socket: &Path) -> io::Result<PathBuf> {
+    let mut p = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => match dirs::home_dir() {
+            Some(home) => home.join(".local").join("state"),
+            None => socket.parent().unwrap().to_path_buf(),
+        },
+    };
+    p.push("rimer");
+    DirBuilder::new().recursive(true).create(&p)?;
+    Ok(p)
+}
+
+/// State file of server listening on `socket`, like `state.json` or
+/// `state-work.json`, see `suffix`.
+pub fn path(socket: &Path) -> io::Result<PathBuf> {
+    file(socket, "state")
+}
+
+/// Schedules file of server listening on `socket`, named like `path`.
+pub fn schedules_path(socket: &Path) -> io::Result<PathBuf> {
+    file(socket, "schedules")
+}
+
+fn file(socket: &Path, kind: &str) -> io::Result<PathBuf> {
+    Ok(dir(socket)?.join(format!("{}{}.json", kind, suffix(socket)?)))
+}
+
+/// Tells files of servers apart: it is empty for default server, like
+/// `-work` for `--instance work` and like `-timers-<hash>` for any other
+/// socket, hash being of its whole path, so that no two sockets share files.
+pub fn suffix(socket: &Path) -> io::Result<String> {
+    let stem = socket.file_stem().unwrap_or_default().to_string_lossy();
+    let instance = match stem.strip_prefix("rimer-") {
+        Some(instance) => Some(Some(instance)),
+        None if stem == "rimer" => Some(None),
+        None => None,
+    };
+    if let Some(instance) = instance {
+        if socket::path(instance)? == socket {
+            return Ok(instance.map_or_else(String::new, |i| format!("-{}", i)));
+        }
+    }
+
+    let dir = match socket.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let path = fs::canonicalize(dir)?.join(socket.file_name().unwrap_or_default());
+    Ok(format!(
+        "-{}-{:016x}",
+        stem,
+        hash(path.as_os_str().as_bytes())
+    ))
+}
+
+/// FNV-1a, unlike `DefaultHasher` it stays the same across builds.
+fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<Record>> {
+    match File::open(path) {
+        Ok(file) => Ok(serde_json::from_reader(file)?),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Schedules registered at runtime, they are few and rarely change, so they
+/// are written right away instead of by store thread.
+pub fn load_schedules(path: &Path) -> io::Result<Schedules> {
+    match File::open(path) {
+        Ok(file) => Ok(serde_json::from_reader(file)?),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Schedules::new()),
+        Err(error) => Err(error),
+    }
+}
+
+pub fn save_schedules(path: &Path, schedules: &Schedules) -> io::Result<()> {
+    write(path, schedules)
+}
+
+fn run(path: PathBuf, receiver: Receiver<Message>) {
+    let mut records = HashMap::new();
+
+    for message in receiver {
+        match message {
+            Message::Save { record } => {
+                records.insert(record.name.clone(), *record);
+            }
+            Message::Remove { name } => {
+                records.remove(&*name);
+            }
+            Message::Quit => break,
+        }
+
+        if let Err(error) = write(&path, &records.values().collect::<Vec<_>>()) {
+            error!("Cannot write {}: {}", path.display(), error);
+        }
+    }
+}
+
+fn write<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    serde_json::to_writer(File::create(&tmp)?, value)?;
+    fs::rename(tmp, path)
+}
@@ -1,5 +1,16 @@
-use super::updater::{self as u, Snapshot};
-use crate::state::State;
+use super::{
+    store::{self, Record},
+    updater::{self as u, Snapshot},
+};
+use crate::{
+    chain::{Chain, Count},
+    clock::Clock,
+    cycle::Cycle,
+    duration,
+    event::{Event, Hooks},
+    response::Error,
+    state::State,
+};
 use std::{
     sync::{
         mpsc::{channel, Receiver, RecvTimeoutError, Sender},
@@ -9,68 +20,199 @@ use std::{
     time::{Duration, SystemTime},
 };
 
+/// Timer that finishes later than this has missed its end.
+const OVERDUE_THRESHOLD: Duration = Duration::from_secs(2);
+
 pub struct Timer {
     handle: thread::JoinHandle<()>,
     queue: Sender<Message>,
+    alarm: bool,
 }
 
 enum Message {
     Pause,
     Resume,
     Halt,
+    /// Stops timer without halting it, so that it is restored on next start.
+    Detach,
     Report,
     ConfirmHalt,
+    Jump,
+    Change {
+        change: Change,
+        reply: Sender<Result<(), Error>>,
+    },
+}
+
+/// Change of a live timer, see `Timer::change`.
+pub enum Change {
+    Extend {
+        by: Duration,
+    },
+    Shorten {
+        by: Duration,
+    },
+    Set {
+        duration: Option<Duration>,
+        step: Option<Duration>,
+        arg: Option<String>,
+    },
+    Rename {
+        name: Arc<String>,
+    },
+}
+
+#[derive(Clone)]
+pub struct Queues {
+    pub update: Sender<u::Message>,
+    /// Timer sends its spec and final state there when it is over.
+    pub halt: Sender<(Spec, State)>,
+    pub report: Sender<Snapshot>,
+    pub store: Sender<store::Message>,
+}
+
+/// What the timer was asked to do.
+#[derive(Clone)]
+pub struct Spec {
+    pub name: Arc<String>,
+    pub duration: Option<Duration>,
+    pub step: Duration,
+    /// Clock running timer counts time on, alarms follow wall-clock time.
+    pub clock: Clock,
+    pub arg: Arc<String>,
+    pub callback: Option<Arc<String>>,
+    pub hooks: Arc<Hooks>,
+    pub cycle: Option<Arc<Cycle>>,
+    pub chain: Option<Arc<Chain>>,
+    /// How many times timer runs, `None` is once.
+    pub repeat: Option<Count>,
+    /// Pause between runs of repeating timer.
+    pub gap: Option<Duration>,
+    /// Wall-clock time alarm goes off at.
+    pub at: Option<SystemTime>,
+    /// Keep counting after duration until halted.
+    pub overtime: bool,
 }
 
 struct Inner {
-    name: Arc<String>,
-    duration: Duration,
-    step: Duration,
+    spec: Spec,
+    timeout: Duration,
     receiver: Receiver<Message>,
-    update_queue: Sender<u::Message>,
-    halt_queue: Sender<Arc<String>>,
-    report_queue: Sender<Snapshot>,
-    start_time: SystemTime,
+    queues: Queues,
+    /// Reading of spec clock when timer last started running, and its elapsed
+    /// time by then.
+    resumed: Duration,
+    resumed_elapsed: Duration,
     state: State,
+    event: Event,
     elapsed: Duration,
-    arg: Arc<String>,
+    /// Run of repeating timer, counts from 1.
+    iteration: u32,
+    /// How late the last run finished, if it missed its end.
+    overdue: Option<Duration>,
+    /// Rest of the gap as of `resumed`, while repeating timer is in one.
+    gap_left: Option<Duration>,
 }
 
 impl Timer {
-    pub fn spawn(
-        name: Arc<String>,
-        duration: Duration,
-        step: Duration,
-        update_queue: Sender<u::Message>,
-        halt_queue: Sender<Arc<String>>,
-        report_queue: Sender<Snapshot>,
-        arg: Arc<String>,
+    pub fn spawn(spec: Spec, queues: Queues) -> Self {
+        Self::launch(
+            spec,
+            State::Running,
+            Event::Start,
+            Duration::from_secs(0),
+            1,
+            None,
+            queues,
+        )
+    }
+
+    pub fn restore(record: Record, queues: Queues) -> Self {
+        let counted = record.start_time.elapsed().unwrap_or(record.elapsed);
+        let (elapsed, gap_left) = match record.gap_left {
+            // Gap went on while server was down, unless it was paused.
+            Some(left) if !record.state.is_paused() => {
+                let down = counted.saturating_sub(record.elapsed);
+                (record.elapsed, Some(left.saturating_sub(down)))
+            }
+            Some(left) => (record.elapsed, Some(left)),
+            None if record.state.is_paused() => (record.elapsed, None),
+            None => (counted, None),
+        };
+
+        let spec = Spec {
+            name: Arc::new(record.name),
+            duration: record.duration,
+            step: record.step,
+            clock: record.clock,
+            arg: Arc::new(record.arg),
+            callback: record.callback.map(Arc::new),
+            hooks: Arc::new(record.hooks),
+            cycle: record.cycle.map(Arc::new),
+            chain: record.chain.map(Arc::new),
+            repeat: record.repeat,
+            gap: record.gap,
+            at: record.at,
+            overtime: record.overtime,
+        };
+        // Records older than repeating timers have no iteration.
+        let iteration = record.iteration.max(1);
+
+        Self::launch(
+            spec,
+            record.state,
+            Event::Step,
+            elapsed,
+            iteration,
+            gap_left,
+            queues,
+        )
+    }
+
+    fn launch(
+        spec: Spec,
+        state: State,
+        event: Event,
+        elapsed: Duration,
+        iteration: u32,
+        gap_left: Option<Duration>,
+        queues: Queues,
     ) -> Self {
-        debug_assert_ne!(duration.as_secs(), 0);
-        debug_assert_ne!(step.as_secs(), 0);
+        debug_assert!(!spec.duration.is_some_and(|d| d.is_zero()));
+        debug_assert!(!spec.step.is_zero());
 
         let (queue, receiver) = channel();
+        let alarm = spec.at.is_some();
 
         let handle = thread::Builder::new()
-            .name("timer ".to_string() + &name)
+            .name("timer ".to_string() + &spec.name)
             .spawn(move || {
                 run(Inner {
-                    name,
-                    duration,
-                    step,
+                    timeout: spec.step,
+                    resumed: spec.clock.now(),
+                    spec,
                     receiver,
-                    update_queue,
-                    halt_queue,
-                    report_queue,
-                    start_time: SystemTime::now(),
-                    state: State::Running,
-                    elapsed: Duration::from_secs(0),
-                    arg,
+                    queues,
+                    resumed_elapsed: elapsed,
+                    state,
+                    event,
+                    elapsed,
+                    iteration,
+                    overdue: None,
+                    gap_left,
                 })
             })
             .unwrap();
 
-        Self { handle, queue }
+        Self {
+            handle,
+            queue,
+            alarm,
+        }
+    }
+
+    pub fn is_alarm(&self) -> bool {
+        self.alarm
     }
 
     pub fn pause(&self) {
@@ -85,6 +227,10 @@ impl Timer {
         self.queue.send(Message::Halt).unwrap();
     }
 
+    pub fn detach(&self) {
+        self.queue.send(Message::Detach).unwrap();
+    }
+
     pub fn report(&self) {
         self.queue.send(Message::Report).unwrap();
     }
@@ -93,36 +239,96 @@ impl Timer {
         self.queue.send(Message::ConfirmHalt).unwrap();
     }
 
+    /// Changes timer and waits for it to be applied, so that timer that is
+    /// already over is not renamed behind server's back.
+    pub fn change(&self, change: Change) -> Result<(), Error> {
+        let (reply, result) = channel();
+        self.queue.send(Message::Change { change, reply }).unwrap();
+        result.recv().unwrap()
+    }
+
+    /// Tells timer that system clock jumped, so that it calls back right away.
+    pub fn jump(&self) {
+        self.queue.send(Message::Jump).unwrap();
+    }
+
     pub fn join(self) {
         self.handle.join().unwrap();
     }
 }
 
 fn run(mut inner: Inner) {
-    if inner.step > inner.duration {
-        inner.step = inner.duration;
-    }
+    inner.update_timeout();
+    inner.send_store();
 
-    while inner.elapsed < inner.duration {
-        inner.send_update();
+    let (mut state, mut event) = (State::Finished, Event::Finish);
+
+    'iterations: loop {
+        // Timer restored in its gap goes on with it.
+        if inner.gap_left.is_some() {
+            match inner.wait_gap() {
+                Message::Halt => {
+                    state = State::Halted;
+                    event = Event::Halt;
+                    break;
+                }
+                Message::Detach => return,
+                _ => inner.start_iteration(),
+            }
+        }
+
+        while !inner.is_finished() {
+            inner.send_update();
+            inner.event = Event::Step;
+
+            let message = if inner.state.is_paused() {
+                inner.wait_resume()
+            } else {
+                inner.wait_message()
+            };
+
+            match message {
+                Message::Halt => {
+                    state = State::Halted;
+                    event = Event::Halt;
+                    break 'iterations;
+                }
+                Message::Detach => return,
+                _ => (),
+            }
+
+            inner.update_elapsed();
+            inner.start_overtime();
+            inner.update_timeout();
+        }
+        inner.check_overdue();
 
-        if let Message::Halt = inner.wait_message() {
+        if !inner.repeats() {
             break;
         }
+        inner.state = State::Finished;
+        inner.event = Event::Finish;
+        inner.send_update();
 
-        inner.update_elapsed();
-        inner.update_step();
+        match inner.spec.gap {
+            Some(gap) => inner.gap_left = Some(gap),
+            None => inner.start_iteration(),
+        }
     }
 
-    inner.state = State::Halted;
+    inner.state = state;
+    inner.event = event;
 
     inner.send_update();
     inner.send_halt();
 
     for message in &inner.receiver {
         match message {
-            Message::ConfirmHalt => break,
+            Message::ConfirmHalt | Message::Detach => break,
             Message::Report => inner.send_report(),
+            Message::Change { reply, .. } => {
+                let _ = reply.send(Err(Error::NameNotExists));
+            }
             _ => (),
         }
     }
@@ -130,22 +336,29 @@ fn run(mut inner: Inner) {
 
 impl Inner {
     fn wait_message(&mut self) -> Message {
-        match self.receiver.recv_timeout(self.step) {
+        match self.receiver.recv_timeout(self.timeout) {
             Ok(Message::Pause) => {
+                self.update_elapsed();
                 self.state = State::Paused;
-                self.send_update();
-                if let Message::Halt = self.wait_resume() {
-                    return Message::Halt;
-                }
+                self.event = Event::Pause;
+                self.send_store();
             }
             Ok(Message::Halt) => {
                 self.update_elapsed();
                 return Message::Halt;
             }
+            Ok(Message::Detach) => return Message::Detach,
             Ok(Message::Report) => {
                 self.update_elapsed();
                 self.send_report();
             }
+            Ok(Message::Jump) => self.event = Event::Jump,
+            Ok(Message::Change { change, reply }) => {
+                self.update_elapsed();
+                if self.change(change, reply) {
+                    self.event = Event::Change;
+                }
+            }
             Err(RecvTimeoutError::Timeout) | Ok(Message::Resume) => (),
             Ok(Message::ConfirmHalt) | Err(RecvTimeoutError::Disconnected) => {
                 unreachable!()
@@ -155,77 +368,349 @@ impl Inner {
     }
 
     fn wait_resume(&mut self) -> Message {
-        self.update_elapsed();
         loop {
             match self.receiver.recv() {
                 Ok(Message::Resume) => {
-                    self.state = State::Running;
-                    self.start_time = SystemTime::now().checked_sub(self.elapsed).unwrap();
+                    self.state = if self.is_overtime() {
+                        State::Overtime
+                    } else {
+                        State::Running
+                    };
+                    self.event = Event::Resume;
+                    self.resume();
+                    self.send_store();
                     break;
                 }
-                Ok(Message::Pause) => (),
+                Ok(Message::Pause) | Ok(Message::Jump) => (),
                 Ok(Message::Report) => {
                     self.send_report();
                 }
                 Ok(Message::Halt) => {
                     return Message::Halt;
                 }
+                Ok(Message::Detach) => return Message::Detach,
+                // Outer loop calls back right away, still paused.
+                Ok(Message::Change { change, reply }) => {
+                    if self.change(change, reply) {
+                        self.event = Event::Change;
+                        break;
+                    }
+                }
                 Ok(Message::ConfirmHalt) | Err(_) => unreachable!(),
             }
         }
         Message::Resume
     }
 
+    /// Waits out the rest of the gap before next iteration, unless halted or
+    /// detached.  Gap can be paused like the timer itself.
+    fn wait_gap(&mut self) -> Message {
+        if !self.state.is_paused() {
+            self.state = State::Waiting;
+        }
+        self.event = Event::Step;
+        self.resume();
+        self.send_update();
+        self.send_store();
+
+        loop {
+            let message = match self.rest_of_gap() {
+                Some(left) if !self.state.is_paused() => self.receiver.recv_timeout(left),
+                _ => self
+                    .receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match message {
+                Ok(Message::Halt) => return Message::Halt,
+                Ok(Message::Detach) => return Message::Detach,
+                Ok(Message::Report) => self.send_report(),
+                Ok(Message::Pause) if !self.state.is_paused() => {
+                    self.gap_left = self.rest_of_gap();
+                    self.state = State::Paused;
+                    self.event = Event::Pause;
+                    self.send_update();
+                    self.event = Event::Step;
+                    self.send_store();
+                }
+                Ok(Message::Resume) if self.state.is_paused() => {
+                    self.state = State::Waiting;
+                    self.resume();
+                    self.event = Event::Resume;
+                    self.send_update();
+                    self.event = Event::Step;
+                    self.send_store();
+                }
+                Ok(Message::Pause) | Ok(Message::Resume) | Ok(Message::Jump) => (),
+                Ok(Message::Change { change, reply }) => {
+                    if self.change(change, reply) {
+                        self.event = Event::Change;
+                        self.send_update();
+                        self.event = Event::Step;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => return Message::Resume,
+                Ok(Message::ConfirmHalt) | Err(RecvTimeoutError::Disconnected) => {
+                    unreachable!()
+                }
+            }
+        }
+    }
+
+    /// Rest of the gap, if timer is in one.
+    fn rest_of_gap(&self) -> Option<Duration> {
+        let left = self.gap_left?;
+        if self.state.is_paused() {
+            return Some(left);
+        }
+        let waited = self.spec.clock.now().saturating_sub(self.resumed);
+        Some(left.saturating_sub(waited))
+    }
+
+    /// Applies `change` and replies whether it could, returns whether it did.
+    fn change(&mut self, change: Change, reply: Sender<Result<(), Error>>) -> bool {
+        let result = self.apply(change);
+        let changed = result.is_ok();
+        if changed {
+            self.send_store();
+        }
+        let _ = reply.send(result);
+        changed
+    }
+
+    fn apply(&mut self, change: Change) -> Result<(), Error> {
+        // Change must not end the run as if timer missed its end.  Timer in
+        // its gap gets new duration for the next run, timer in overtime has
+        // ended already.
+        let counting = self.gap_left.is_none() && self.state != State::Overtime;
+        let elapsed = self.elapsed;
+        let ends = move |duration: Duration| counting && duration <= elapsed;
+
+        let spec = &mut self.spec;
+        let error = |message: &str| Error::Generic {
+            message: message.into(),
+        };
+        let no_duration = || error("Stopwatch has no duration to change");
+        let too_long = || error("Timer would be too long");
+        match change {
+            Change::Extend { by } => {
+                let duration = spec.duration.ok_or_else(no_duration)?;
+                let duration = duration.checked_add(by).ok_or_else(too_long)?;
+                let at = match spec.at {
+                    Some(at) => Some(at.checked_add(by).ok_or_else(too_long)?),
+                    None => None,
+                };
+                spec.duration = Some(duration);
+                spec.at = at;
+            }
+            Change::Shorten { by } => {
+                let duration = spec.duration.ok_or_else(no_duration)?;
+                match duration.checked_sub(by) {
+                    Some(duration) if ends(duration) && !duration.is_zero() => {
+                        return Err(error("Cannot shorten timer below its elapsed time"));
+                    }
+                    Some(duration) if !duration.is_zero() => {
+                        spec.duration = Some(duration);
+                        // Alarm still ends after it was added.
+                        spec.at = spec.at.map(|at| at - by);
+                    }
+                    _ => return Err(error("Cannot shorten timer by its whole duration or more")),
+                }
+            }
+            Change::Set {
+                duration,
+                step,
+                arg,
+            } => {
+                if duration.is_some() && spec.at.is_some() {
+                    return Err(error(
+                        "Alarm duration cannot be set, extend or shorten it instead",
+                    ));
+                }
+                if duration.is_some_and(ends) {
+                    return Err(error("Cannot set duration below elapsed time"));
+                }
+                spec.duration = duration.or(spec.duration);
+                spec.step = step.unwrap_or(spec.step);
+                if let Some(arg) = arg {
+                    spec.arg = Arc::new(arg);
+                }
+            }
+            Change::Rename { name } => {
+                store::remove(&self.queues.store, Arc::clone(&spec.name));
+                u::rename(
+                    &self.queues.update,
+                    Arc::clone(&spec.name),
+                    Arc::clone(&name),
+                );
+                spec.name = name;
+            }
+        }
+
+        // Timer given more time goes back from overtime.
+        if self.state == State::Overtime && !self.is_overtime() {
+            self.state = State::Running;
+        }
+        Ok(())
+    }
+
+    /// Switches timer that keeps counting after its end to overtime, which
+    /// counts as finishing for callbacks.
+    fn start_overtime(&mut self) {
+        if self.state == State::Running && self.is_overtime() {
+            self.state = State::Overtime;
+            self.event = Event::Finish;
+            self.check_overdue();
+            self.send_store();
+        }
+    }
+
+    fn is_overtime(&self) -> bool {
+        self.spec.overtime
+            && self
+                .spec
+                .duration
+                .is_some_and(|duration| self.elapsed >= duration)
+    }
+
+    /// Notices that timer finished late, since it cannot wake up in suspend
+    /// or when its clock is set.
+    fn check_overdue(&mut self) {
+        let overdue = self
+            .spec
+            .duration
+            .map(|duration| self.elapsed.saturating_sub(duration))
+            .filter(|&overdue| overdue >= OVERDUE_THRESHOLD);
+        if let Some(overdue) = overdue {
+            warn!(
+                "Timer {} finished {} late",
+                self.spec.name,
+                duration::format(overdue)
+            );
+        }
+        self.overdue = overdue;
+    }
+
+    fn start_iteration(&mut self) {
+        self.iteration += 1;
+        self.overdue = None;
+        self.gap_left = None;
+        self.state = State::Running;
+        self.event = Event::Start;
+        self.elapsed = Duration::from_secs(0);
+        self.resume();
+        self.update_timeout();
+        self.send_store();
+    }
+
+    fn repeats(&self) -> bool {
+        self.spec
+            .repeat
+            .is_some_and(|repeat| repeat.allows(self.iteration + 1))
+    }
+
     fn make_snapshot(&self) -> Snapshot {
         Snapshot {
-            name: Arc::clone(&self.name),
-            duration: self.duration,
+            name: Arc::clone(&self.spec.name),
+            duration: self.spec.duration,
+            step: self.spec.step,
             elapsed: self.elapsed,
             state: self.state,
-            arg: Arc::clone(&self.arg),
+            arg: Arc::clone(&self.spec.arg),
+            callback: self.spec.callback.clone(),
+            hooks: Arc::clone(&self.spec.hooks),
+            cycle: self.spec.cycle.clone(),
+            iteration: self.spec.repeat.map(|_| self.iteration),
+            at: self.spec.at,
+            overdue: self.overdue,
+            overtime: self.spec.overtime,
+            event: self.event,
+        }
+    }
+
+    fn make_record(&self) -> Record {
+        Record {
+            name: self.spec.name.to_string(),
+            duration: self.spec.duration,
+            step: self.spec.step,
+            clock: self.spec.clock,
+            arg: self.spec.arg.to_string(),
+            callback: self.spec.callback.as_ref().map(|c| c.to_string()),
+            hooks: (*self.spec.hooks).clone(),
+            cycle: self.spec.cycle.as_deref().cloned(),
+            chain: self.spec.chain.as_deref().cloned(),
+            repeat: self.spec.repeat,
+            gap: self.spec.gap,
+            iteration: self.iteration,
+            at: self.spec.at,
+            overtime: self.spec.overtime,
+            gap_left: self.rest_of_gap(),
+            state: self.state,
+            elapsed: self.elapsed,
+            start_time: SystemTime::now()
+                .checked_sub(self.current_elapsed())
+                .unwrap(),
         }
     }
 
     fn send_update(&self) {
         let snapshot = self.make_snapshot();
-        u::update(&self.update_queue, snapshot);
+        u::update(&self.queues.update, snapshot);
     }
 
     fn send_halt(&self) {
-        self.halt_queue.send(Arc::clone(&self.name)).unwrap();
+        store::remove(&self.queues.store, Arc::clone(&self.spec.name));
+        self.queues
+            .halt
+            .send((self.spec.clone(), self.state))
+            .unwrap();
     }
 
     fn send_report(&self) {
         let snapshot = self.make_snapshot();
-        self.report_queue.send(snapshot).unwrap();
+        self.queues.report.send(snapshot).unwrap();
     }
 
-    fn update_step(&mut self) {
-        if self.elapsed < self.duration {
-            if let Some(elapsed_plus_step) = self.elapsed.checked_add(self.step) {
-                if self.duration < elapsed_plus_step {
-                    self.step = self.duration - self.elapsed;
-                }
-            } else {
-                self.step = self.duration - self.elapsed;
+    fn send_store(&self) {
+        store::save(&self.queues.store, self.make_record());
+    }
+
+    fn is_finished(&self) -> bool {
+        !self.spec.overtime
+            && self
+                .spec
+                .duration
+                .is_some_and(|duration| self.elapsed >= duration)
+    }
+
+    fn update_timeout(&mut self) {
+        self.timeout = match self.spec.duration {
+            Some(duration) if self.elapsed < duration => {
+                self.spec.step.min(duration - self.elapsed)
             }
-        }
+            _ => self.spec.step,
+        };
+    }
+
+    /// Starts counting from current elapsed time.
+    fn resume(&mut self) {
+        self.resumed = self.spec.clock.now();
+        self.resumed_elapsed = self.elapsed;
     }
 
     fn update_elapsed(&mut self) {
-        if let Ok(new_elapsed) = self.start_time.elapsed() {
-            self.elapsed = new_elapsed;
-        } else {
-            if let Some(qty) = SystemTime::now().checked_sub(self.elapsed) {
-                self.start_time = qty;
-            } else {
-                eprintln!(
-                    "Cant update qty: {:#?} {:#?} {:#?}",
-                    SystemTime::now(),
-                    self.start_time,
-                    self.elapsed
-                );
-            }
+        self.elapsed = self.current_elapsed();
+    }
+
+    fn current_elapsed(&self) -> Duration {
+        match (self.spec.at, self.spec.duration) {
+            (Some(at), Some(duration)) => match at.duration_since(SystemTime::now()) {
+                Ok(remaining) => duration.saturating_sub(remaining),
+                Err(past) => duration + past.duration(),
+            },
+            // Gap does not count towards the run.
+            _ if self.state.is_paused() || self.gap_left.is_some() => self.elapsed,
+            _ => self.resumed_elapsed + self.spec.clock.now().saturating_sub(self.resumed),
         }
     }
 }
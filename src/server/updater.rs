@@ -1,44 +1,144 @@
-use crate::state::State;
+use crate::{
+    cycle::{Cycle, Position},
+    event::{Event, Hooks},
+    state::State,
+};
+use serde::Serialize;
 use std::{
-    process::Command,
+    collections::{HashMap, VecDeque},
+    io, iter,
+    process::{Child, Command, Stdio},
     sync::{
-        mpsc::{channel, Receiver, Sender},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
         Arc,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
 pub struct Updater {
     pub handle: thread::JoinHandle<()>,
     pub queue: Sender<Message>,
 }
 
 pub enum Message {
-    Update { snapshot: Snapshot },
+    Update {
+        snapshot: Snapshot,
+    },
+    /// Timer is renamed, its later updates come under the new name.
+    Rename {
+        from: Arc<String>,
+        to: Arc<String>,
+    },
+    Reload {
+        command: String,
+        hooks: Hooks,
+    },
     Quit,
 }
 
+/// Settings for callback processes.
+pub struct Options {
+    /// Callback is killed if it runs longer than this.
+    pub timeout: Duration,
+    /// Maximum number of callbacks running at the same time.
+    pub jobs: usize,
+    /// Whether snapshot is written as json to callback stdin.
+    pub json: bool,
+    /// Whether callbacks get durations in milliseconds instead of seconds.
+    pub millis: bool,
+}
+
+/// Remembers when each timer last called back, so that every timer keeps its
+/// own `step` cadence.
+#[derive(Default)]
+struct Throttle {
+    last_update: HashMap<Arc<String>, Duration>,
+}
+
+/// Callback and hook processes of one snapshot.
+struct Job {
+    name: Arc<String>,
+    children: Vec<Child>,
+    deadline: Instant,
+}
+
+struct Inner {
+    command: String,
+    hooks: Hooks,
+    options: Options,
+    throttle: Throttle,
+    running: Vec<Job>,
+    pending: VecDeque<Snapshot>,
+}
+
 pub struct Snapshot {
     pub name: Arc<String>,
-    pub duration: Duration,
+    pub duration: Option<Duration>,
+    pub step: Duration,
     pub elapsed: Duration,
     pub state: State,
     pub arg: Arc<String>,
+    pub callback: Option<Arc<String>>,
+    pub hooks: Arc<Hooks>,
+    pub cycle: Option<Arc<Cycle>>,
+    /// Iteration of repeating timer, counts from 1.
+    pub iteration: Option<u32>,
+    /// Wall-clock time alarm goes off at.
+    pub at: Option<SystemTime>,
+    /// How late timer finished, if it missed its end.
+    pub overdue: Option<Duration>,
+    /// Whether timer keeps counting after its end.
+    pub overtime: bool,
+    pub event: Event,
+}
+
+/// Snapshot json written to callback stdin.
+#[derive(Serialize)]
+struct Payload<'a> {
+    name: &'a str,
+    duration: Option<Duration>,
+    elapsed: Duration,
+    state: State,
+    arg: &'a str,
+    cycle: Option<Position>,
+    iteration: Option<u32>,
+    at: Option<SystemTime>,
+    overdue: Option<Duration>,
+    event: Event,
 }
 
 impl Updater {
-    pub fn spawn(command: String) -> Self {
+    pub fn spawn(command: String, hooks: Hooks, options: Options) -> Self {
         let (queue, receiver) = channel();
 
         let handle = thread::Builder::new()
             .name("updater".into())
-            .spawn(|| run(command, receiver))
+            .spawn(|| {
+                run(
+                    Inner {
+                        command,
+                        hooks,
+                        options,
+                        throttle: Throttle::default(),
+                        running: Vec::new(),
+                        pending: VecDeque::new(),
+                    },
+                    receiver,
+                )
+            })
             .unwrap();
 
         Updater { queue, handle }
     }
 
+    /// Replaces server callback and hooks.
+    pub fn reload(&self, command: String, hooks: Hooks) {
+        self.queue.send(Message::Reload { command, hooks }).unwrap();
+    }
+
     pub fn quit(&self) {
         self.queue.send(Message::Quit).unwrap();
     }
@@ -52,45 +152,439 @@ pub fn update(queue: &Sender<Message>, snapshot: Snapshot) {
     queue.send(Message::Update { snapshot }).unwrap();
 }
 
-fn run(command: String, receiver: Receiver<Message>) {
-    let mut last_update = std::time::Duration::from_secs(0);
+pub fn rename(queue: &Sender<Message>, from: Arc<String>, to: Arc<String>) {
+    queue.send(Message::Rename { from, to }).unwrap();
+}
+
+fn run(mut inner: Inner, receiver: Receiver<Message>) {
+    let mut quit = false;
+
+    // After quit, keep going until every pending callback has been delivered.
+    while !quit || inner.is_busy() {
+        let message = if quit {
+            thread::sleep(POLL_INTERVAL);
+            None
+        } else if inner.is_busy() {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(message) => Some(message),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => Some(Message::Quit),
+            }
+        } else {
+            Some(receiver.recv().unwrap_or(Message::Quit))
+        };
 
-    for update in receiver {
-        match update {
-            Message::Update { snapshot } => handle_update(snapshot, &mut last_update, &command),
-            Message::Quit => break,
+        match message {
+            Some(Message::Update { snapshot }) => inner.handle_update(snapshot),
+            Some(Message::Rename { from, to }) => inner.throttle.rename(&from, to),
+            Some(Message::Reload { command, hooks }) => inner.handle_reload(command, hooks),
+            Some(Message::Quit) => quit = true,
+            None => (),
         }
+
+        inner.reap();
+        inner.launch();
     }
 }
 
-fn handle_update(s: Snapshot, last_update: &mut Duration, command: &str) {
-    let remaining = if s.duration > s.elapsed {
-        s.duration - s.elapsed
-    } else {
-        Duration::from_secs(0)
-    };
-
-    if s.elapsed.as_secs() == 0
-        || last_update.as_secs() == 0
-        || remaining <= *last_update
-        || s.state.is_halted()
-        || s.state.is_paused()
-    {
-        *last_update = if s.state.is_paused() {
-            Duration::from_secs(0)
-        } else {
-            remaining
+impl Inner {
+    fn is_busy(&self) -> bool {
+        !self.running.is_empty() || !self.pending.is_empty()
+    }
+
+    fn handle_update(&mut self, s: Snapshot) {
+        if self.throttle.pass(&s) {
+            enqueue(&mut self.pending, s);
+        }
+    }
+
+    fn handle_reload(&mut self, command: String, hooks: Hooks) {
+        if command != self.command {
+            info!("Callback is now {}", command);
+            self.command = command;
+        }
+        for (event, hook) in &hooks {
+            if self.hooks.get(event) != Some(hook) {
+                info!("Hook on {} is now {}", event, hook);
+            }
+        }
+        for event in self.hooks.keys().filter(|e| !hooks.contains_key(e)) {
+            info!("Hook on {} is removed", event);
+        }
+        self.hooks = hooks;
+    }
+
+    fn reap(&mut self) {
+        let now = Instant::now();
+        self.running.retain_mut(|job| {
+            let Job {
+                name,
+                children,
+                deadline,
+            } = job;
+
+            children.retain_mut(|child| match child.try_wait() {
+                Ok(Some(_)) => false,
+                Ok(None) if now >= *deadline => {
+                    warn!("Callback for {} timed out, killing it", name);
+                    if let Err(error) = child.kill().and_then(|_| child.wait()) {
+                        error!("Cannot kill callback for {}: {}", name, error);
+                    }
+                    false
+                }
+                Ok(None) => true,
+                Err(error) => {
+                    error!("Cannot wait callback for {}: {}", name, error);
+                    false
+                }
+            });
+            !children.is_empty()
+        });
+    }
+
+    fn launch(&mut self) {
+        while self.running.len() < self.options.jobs {
+            let running = &self.running;
+            let next = self
+                .pending
+                .iter()
+                .position(|s| running.iter().all(|job| job.name != s.name));
+
+            let s = match next.and_then(|i| self.pending.remove(i)) {
+                Some(s) => s,
+                None => break,
+            };
+
+            let callback = s.callback.as_deref().unwrap_or(&self.command);
+            let millis = self.options.millis;
+            let mut commands = vec![command(callback, &s, millis)];
+            for event in events(&s) {
+                if let Some(hook) = s.hooks.get(&event).or_else(|| self.hooks.get(&event)) {
+                    let mut hook = command(hook, &s, millis);
+                    hook.arg(event.to_string());
+                    commands.push(hook);
+                }
+            }
+
+            let json = self.options.json;
+            let children: Vec<Child> = commands
+                .iter_mut()
+                .filter_map(|command| {
+                    spawn(command, &s, json)
+                        .map_err(|e| error!("Cannot run callback for {}: {}", s.name, e))
+                        .ok()
+                })
+                .collect();
+
+            if !children.is_empty() {
+                self.running.push(Job {
+                    name: s.name,
+                    children,
+                    deadline: Instant::now() + self.options.timeout,
+                });
+            }
+        }
+    }
+}
+
+/// Events whose hooks run on snapshot: its own one, and missed one when timer
+/// finished late.
+fn events(s: &Snapshot) -> impl Iterator<Item = Event> {
+    let missed = s.event == Event::Finish && s.overdue.is_some();
+    iter::once(s.event).chain(missed.then_some(Event::Missed))
+}
+
+fn command(program: &str, s: &Snapshot, millis: bool) -> Command {
+    let amount = |duration: Duration| amount(duration, millis);
+    let mut command = Command::new(program);
+    command
+        .arg(&*s.name)
+        .arg(amount(s.elapsed).to_string())
+        .arg(total(s.duration, millis))
+        .arg(s.state.to_string())
+        .arg(&*s.arg)
+        .env("RIMER_NAME", &*s.name)
+        .env("RIMER_ELAPSED", amount(s.elapsed).to_string())
+        .env("RIMER_STATE", s.state.to_string())
+        .env("RIMER_ARG", &*s.arg)
+        .env("RIMER_EVENT", s.event.to_string());
+
+    // Stopwatch has neither, so they are left unset.
+    if let Some(duration) = s.duration {
+        // Negative past the end in overtime, timer that finished late has
+        // none left.
+        let remaining = match duration.checked_sub(s.elapsed) {
+            Some(remaining) => amount(remaining).to_string(),
+            None if s.overtime => match amount(s.elapsed - duration) {
+                0 => "0".into(),
+                over => format!("-{}", over),
+            },
+            None => "0".into(),
+        };
+        command
+            .env("RIMER_DURATION", amount(duration).to_string())
+            .env("RIMER_REMAINING", remaining);
+    }
+    if let Some(overdue) = s.overdue {
+        command.env("RIMER_OVERDUE", amount(overdue).to_string());
+    }
+    if let Some(iteration) = s.iteration {
+        command.env("RIMER_ITERATION", iteration.to_string());
+    }
+    if let Some(cycle) = &s.cycle {
+        let position = cycle.position();
+        command
+            .env("RIMER_PHASE", position.phase)
+            .env("RIMER_PHASE_INDEX", position.index.to_string())
+            .env("RIMER_PHASE_COUNT", position.count.to_string());
+    }
+    command
+}
+
+fn spawn(command: &mut Command, s: &Snapshot, json: bool) -> io::Result<Child> {
+    if json {
+        command.stdin(Stdio::piped());
+    }
+
+    let mut child = command.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let payload = Payload {
+            name: &s.name,
+            duration: s.duration,
+            elapsed: s.elapsed,
+            state: s.state,
+            arg: &s.arg,
+            cycle: s.cycle.as_ref().map(|cycle| cycle.position()),
+            iteration: s.iteration,
+            at: s.at,
+            overdue: s.overdue,
+            event: s.event,
         };
+        // Callback is free to exit without reading its stdin.
+        let _ = serde_json::to_writer(&mut stdin, &payload);
+    }
+    Ok(child)
+}
+
+/// Queues snapshot for delivery.  A pending step of the same timer is replaced,
+/// so only the newest one is delivered, but other events are never dropped.
+fn enqueue(pending: &mut VecDeque<Snapshot>, s: Snapshot) {
+    let old = pending
+        .iter_mut()
+        .rev()
+        .take_while(|old| old.name != s.name || old.event == Event::Step)
+        .find(|old| old.name == s.name);
+
+    if let Some(old) = old {
+        *old = s;
+    } else {
+        pending.push_back(s);
+    }
+}
 
-        if let Err(error) = Command::new(command)
-            .arg(&*s.name)
-            .arg(s.elapsed.as_secs().to_string())
-            .arg(s.duration.as_secs().to_string())
-            .arg(s.state.to_string())
-            .arg(&*s.arg)
-            .status()
-        {
-            eprintln!("{}", error);
+fn total(duration: Option<Duration>, millis: bool) -> String {
+    duration.map_or_else(
+        || "-".into(),
+        |duration| amount(duration, millis).to_string(),
+    )
+}
+
+/// Duration as callbacks get it, in whole seconds or milliseconds.
+fn amount(duration: Duration, millis: bool) -> u128 {
+    if millis {
+        duration.as_millis()
+    } else {
+        duration.as_secs().into()
+    }
+}
+
+impl Throttle {
+    /// Keeps cadence of renamed timer.
+    fn rename(&mut self, from: &Arc<String>, to: Arc<String>) {
+        if let Some(last) = self.last_update.remove(from) {
+            self.last_update.insert(to, last);
+        }
+    }
+
+    fn pass(&mut self, s: &Snapshot) -> bool {
+        if s.state.is_over() || s.state.is_paused() {
+            self.last_update.remove(&s.name);
+            return true;
+        }
+
+        let due = s.event != Event::Step
+            || match self.last_update.get(&s.name) {
+                Some(&last) => {
+                    let at_end = s.state != State::Overtime
+                        && s.duration.is_some_and(|duration| s.elapsed >= duration);
+                    last.checked_add(s.step)
+                        .is_some_and(|next| s.elapsed >= next)
+                        || at_end
+                }
+                None => true,
+            };
+
+        if due {
+            self.last_update.insert(Arc::clone(&s.name), s.elapsed);
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(name: &str, step: u64, elapsed: u64, state: State) -> Snapshot {
+        let event = match state {
+            State::Running | State::Waiting | State::Overtime => Event::Step,
+            State::Paused => Event::Pause,
+            State::Halted => Event::Halt,
+            State::Finished => Event::Finish,
         };
+
+        Snapshot {
+            name: Arc::new(name.into()),
+            duration: Some(Duration::from_secs(100)),
+            step: Duration::from_secs(step),
+            elapsed: Duration::from_secs(elapsed),
+            state,
+            arg: Arc::new(String::new()),
+            callback: None,
+            hooks: Arc::new(Hooks::new()),
+            cycle: None,
+            iteration: None,
+            at: None,
+            overdue: None,
+            overtime: false,
+            event,
+        }
+    }
+
+    #[test]
+    fn interleaved_timers_keep_own_step() {
+        let mut throttle = Throttle::default();
+        let updates = [
+            ("fast", 2, 0, true),
+            ("slow", 5, 0, true),
+            ("fast", 2, 2, true),
+            ("slow", 5, 3, false),
+            ("fast", 2, 3, false),
+            ("fast", 2, 4, true),
+            ("slow", 5, 5, true),
+            ("fast", 2, 6, true),
+            ("slow", 5, 8, false),
+            ("slow", 5, 10, true),
+        ];
+
+        for &(name, step, elapsed, expected) in &updates {
+            let s = snapshot(name, step, elapsed, State::Running);
+            assert_eq!(throttle.pass(&s), expected, "{} at {}", name, elapsed);
+        }
+    }
+
+    #[test]
+    fn pause_and_halt_always_pass_and_reset() {
+        let mut throttle = Throttle::default();
+
+        assert!(throttle.pass(&snapshot("a", 10, 0, State::Running)));
+        assert!(throttle.pass(&snapshot("b", 10, 0, State::Running)));
+        assert!(throttle.pass(&snapshot("a", 10, 3, State::Paused)));
+        assert!(!throttle.pass(&snapshot("b", 10, 3, State::Running)));
+        assert!(throttle.pass(&snapshot("a", 10, 3, State::Running)));
+        assert!(throttle.pass(&snapshot("b", 10, 4, State::Halted)));
+        assert!(!throttle
+            .last_update
+            .contains_key(&Arc::new("b".to_string())));
+        assert!(!throttle.pass(&snapshot("a", 10, 5, State::Running)));
+    }
+
+    #[test]
+    fn renamed_timer_keeps_step() {
+        let mut throttle = Throttle::default();
+
+        assert!(throttle.pass(&snapshot("a", 10, 0, State::Running)));
+        throttle.rename(&Arc::new("a".into()), Arc::new("b".into()));
+        assert!(!throttle
+            .last_update
+            .contains_key(&Arc::new("a".to_string())));
+        assert!(!throttle.pass(&snapshot("b", 10, 5, State::Running)));
+        assert!(throttle.pass(&snapshot("b", 10, 10, State::Running)));
+    }
+
+    #[test]
+    fn pending_keeps_newest_snapshot_per_timer() {
+        let mut pending = VecDeque::new();
+
+        enqueue(&mut pending, snapshot("a", 1, 1, State::Running));
+        enqueue(&mut pending, snapshot("b", 1, 1, State::Running));
+        enqueue(&mut pending, snapshot("a", 1, 2, State::Running));
+        enqueue(&mut pending, snapshot("a", 1, 3, State::Halted));
+
+        let pending: Vec<_> = pending
+            .iter()
+            .map(|s| (s.name.as_str(), s.elapsed.as_secs(), s.state.is_over()))
+            .collect();
+        assert_eq!(pending, [("a", 3, true), ("b", 1, false)]);
+    }
+
+    #[test]
+    fn pending_never_drops_events() {
+        let mut pending = VecDeque::new();
+
+        enqueue(&mut pending, snapshot("a", 1, 1, State::Running));
+        enqueue(&mut pending, snapshot("a", 1, 2, State::Paused));
+        enqueue(&mut pending, snapshot("a", 1, 2, State::Running));
+        enqueue(&mut pending, snapshot("a", 1, 3, State::Running));
+
+        let events: Vec<_> = pending
+            .iter()
+            .map(|s| (s.event, s.elapsed.as_secs()))
+            .collect();
+        assert_eq!(events, [(Event::Pause, 2), (Event::Step, 3)]);
+    }
+
+    #[test]
+    fn events_pass_regardless_of_step() {
+        let mut throttle = Throttle::default();
+        let mut resume = snapshot("a", 10, 1, State::Running);
+        resume.event = Event::Resume;
+
+        assert!(throttle.pass(&snapshot("a", 10, 0, State::Running)));
+        assert!(throttle.pass(&resume));
+        assert!(!throttle.pass(&snapshot("a", 10, 2, State::Running)));
+    }
+
+    #[test]
+    fn finish_passes_regardless_of_step() {
+        let mut throttle = Throttle::default();
+
+        assert!(throttle.pass(&snapshot("a", 30, 90, State::Running)));
+        assert!(throttle.pass(&snapshot("a", 30, 100, State::Running)));
+    }
+
+    #[test]
+    fn overtime_keeps_step() {
+        let mut throttle = Throttle::default();
+
+        assert!(throttle.pass(&snapshot("a", 10, 100, State::Running)));
+        assert!(!throttle.pass(&snapshot("a", 10, 105, State::Overtime)));
+        assert!(throttle.pass(&snapshot("a", 10, 110, State::Overtime)));
+    }
+
+    #[test]
+    fn missed_hooks_run_on_late_finish_only() {
+        let mut late = snapshot("a", 10, 105, State::Finished);
+        late.overdue = Some(Duration::from_secs(5));
+        let mut late_step = snapshot("a", 10, 105, State::Running);
+        late_step.overdue = Some(Duration::from_secs(5));
+
+        let events = |s: &Snapshot| events(s).collect::<Vec<_>>();
+        assert_eq!(events(&late), [Event::Finish, Event::Missed]);
+        assert_eq!(events(&late_step), [Event::Step]);
+        assert_eq!(
+            events(&snapshot("a", 10, 100, State::Finished)),
+            [Event::Finish]
+        );
     }
 }
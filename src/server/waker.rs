@@ -0,0 +1,30 @@
+use super::notify;
+use crate::request::Request;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+/// Forwards messages to accept loop and wakes it up by sending
+/// `Request::Wake` to the server itself, so that timers that are over get
+/// followed up right away instead of on the next client request.
+pub fn spawn<T: Send + 'static>(sock: PathBuf) -> (Sender<T>, Receiver<T>) {
+    let (queue, receiver) = channel();
+    let (forward, forwarded) = channel();
+
+    thread::Builder::new()
+        .name("waker".into())
+        .spawn(move || {
+            for message in receiver {
+                if forward.send(message).is_err() {
+                    break;
+                }
+                // Server that is quitting does not answer, nothing to follow up.
+                let _ = notify(&sock, &Request::Wake);
+            }
+        })
+        .unwrap();
+
+    (queue, forwarded)
+}
@@ -1,18 +1,35 @@
-use super::state::State;
+use super::{cycle::Position, duration, state::State};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Display},
     hash::{Hash, Hasher},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 #[derive(Deserialize, Serialize, Eq)]
 pub struct Snapshot {
     pub name: String,
-    pub duration: Duration,
+    pub duration: Option<Duration>,
     pub elapsed: Duration,
     pub state: State,
     pub arg: String,
+    /// Position of timer in its cycle, if any.
+    #[serde(default)]
+    pub cycle: Option<Position>,
+    /// Iteration of repeating timer, counts from 1.
+    #[serde(default)]
+    pub iteration: Option<u32>,
+    /// Wall-clock time alarm goes off at.
+    #[serde(default)]
+    pub at: Option<SystemTime>,
+    /// How late timer finished, if it missed its end.
+    #[serde(default)]
+    pub overdue: Option<Duration>,
+    /// `elapsed` and `duration` for json readers that want plain numbers.
+    #[serde(default)]
+    pub elapsed_ms: u64,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
 }
 
 impl PartialEq for Snapshot {
@@ -27,16 +44,53 @@ impl Hash for Snapshot {
     }
 }
 
-impl Display for Snapshot {
+/// How durations of report lines are written.
+#[derive(Clone, Copy)]
+pub enum Format {
+    Seconds,
+    Millis,
+    /// Like `1h30m`.
+    Human,
+}
+
+impl Format {
+    fn duration(self, duration: Duration) -> String {
+        match self {
+            Self::Seconds => duration.as_secs().to_string(),
+            Self::Millis => duration.as_millis().to_string(),
+            Self::Human => duration::format(duration),
+        }
+    }
+}
+
+impl Snapshot {
+    /// Report line of timer with durations in `format`.
+    pub fn display(&self, format: Format) -> impl Display + '_ {
+        Line {
+            snapshot: self,
+            format,
+        }
+    }
+}
+
+struct Line<'a> {
+    snapshot: &'a Snapshot,
+    format: Format,
+}
+
+impl Display for Line<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Line { snapshot, format } = self;
         write!(
             f,
             "{} {} {} {} {}",
-            self.name,
-            self.elapsed.as_secs(),
-            self.duration.as_secs(),
-            self.state,
-            self.arg
+            snapshot.name,
+            format.duration(snapshot.elapsed),
+            snapshot
+                .duration
+                .map_or_else(|| "-".into(), |duration| format.duration(duration)),
+            snapshot.state,
+            snapshot.arg
         )
     }
 }
@@ -2,35 +2,61 @@ use super::request::Request;
 use std::{
     fs::DirBuilder,
     io,
+    net::Shutdown,
     os::unix::net::{UnixListener, UnixStream},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-pub fn listener() -> io::Result<UnixListener> {
-    let sock = path()?;
-
+pub fn listener(sock: &Path, replace: bool) -> io::Result<UnixListener> {
     if sock.exists() {
-        if let Ok(stream) = UnixStream::connect(&sock) {
-            if let Err(e) = serde_json::to_writer(&stream, &Request::Quit) {
+        if let Ok(stream) = UnixStream::connect(sock) {
+            if !replace {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!(
+                        "Server is already running on {}, use --replace to replace it",
+                        sock.display()
+                    ),
+                ));
+            }
+            if let Err(e) = quit(&stream) {
                 eprintln!("{}", e);
             }
         }
-        std::fs::remove_file(&sock)?;
+        // Old server may have removed its socket on the way out already.
+        match std::fs::remove_file(sock) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => (),
+        }
     }
 
     UnixListener::bind(sock)
 }
 
-pub fn stream() -> io::Result<UnixStream> {
-    UnixStream::connect(path()?)
+/// Asks server to quit and waits until it has, so that it neither takes its
+/// timers along nor removes the socket of the server replacing it.
+fn quit(stream: &UnixStream) -> io::Result<()> {
+    serde_json::to_writer(stream, &Request::Quit)?;
+    stream.shutdown(Shutdown::Write)?;
+    io::copy(&mut &*stream, &mut io::sink())?;
+    Ok(())
 }
 
-pub fn path() -> std::io::Result<PathBuf> {
+pub fn stream(sock: &Path) -> io::Result<UnixStream> {
+    UnixStream::connect(sock)
+}
+
+/// Default socket path of server instance.  Unnamed instance uses
+/// `rimer.socket`, named one uses `rimer-<instance>.socket`.
+pub fn path(instance: Option<&str>) -> std::io::Result<PathBuf> {
     let mut p = dirs::runtime_dir().unwrap_or_else(|| {
         eprintln!("XDG_RUNTIME_DIR is undefined! Using /tmp.");
         PathBuf::from("/tmp")
     });
     DirBuilder::new().recursive(true).create(&p)?;
-    p.push("rimer.socket");
+    match instance {
+        Some(instance) => p.push(format!("rimer-{}.socket", instance)),
+        None => p.push("rimer.socket"),
+    }
     Ok(p)
 }
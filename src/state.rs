@@ -6,23 +6,21 @@ pub enum State {
     Running,
     Paused,
     Halted,
+    Finished,
+    /// Between iterations of repeating timer.
+    Waiting,
+    /// Past its end, counting on until halted.
+    Overtime,
 }
 
 impl State {
     pub fn is_paused(self) -> bool {
-        if let State::Paused = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, State::Paused)
     }
 
-    pub fn is_halted(self) -> bool {
-        if let State::Halted = self {
-            true
-        } else {
-            false
-        }
+    /// Whether timer has stopped, either halted by user or finished.
+    pub fn is_over(self) -> bool {
+        matches!(self, State::Halted | State::Finished)
     }
 }
 
@@ -32,6 +30,9 @@ impl Display for State {
             Self::Running => write!(f, "running"),
             Self::Paused => write!(f, "paused"),
             Self::Halted => write!(f, "halted"),
+            Self::Finished => write!(f, "finished"),
+            Self::Waiting => write!(f, "waiting"),
+            Self::Overtime => write!(f, "overtime"),
         }
     }
 }